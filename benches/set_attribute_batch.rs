@@ -0,0 +1,72 @@
+//! Compares [`set_attribute_batch()`] to building the same arguments
+//! for each node.
+//!
+//! This needs 3Delight to be installed. Run with:
+//! ```text
+//! cargo bench --bench set_attribute_batch
+//! ```
+use nsi_3delight::set_attribute_batch;
+use std::time::{Duration, Instant};
+
+const NODES: usize = 10_000;
+const ROUNDS: u32 = 10;
+
+fn time(f: impl Fn()) -> Duration {
+    // Warm up.
+    f();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    // Writes the scene to a file instead of rendering it.
+    let stream = std::env::temp_dir().join("set_attribute_batch.nsi");
+    let ctx = nsi::Context::new(&[
+        nsi::string!("type", "apistream"),
+        nsi::string!("streamfilename", stream.to_str().unwrap()),
+    ])
+    .expect("Could not create ɴsɪ context.");
+
+    let handles = (0..NODES)
+        .map(|i| format!("shader_{}", i))
+        .collect::<Vec<_>>();
+    for handle in &handles {
+        ctx.create(handle.as_str(), nsi::NodeType::Shader, &[]);
+    }
+    let handles = handles.iter().map(|h| h.as_str()).collect::<Vec<_>>();
+
+    let color = [0.18f32, 0.18, 0.18];
+
+    let naive = time(|| {
+        for handle in &handles {
+            ctx.set_attribute(
+                *handle,
+                &[
+                    nsi::string!("shaderfilename", "${DELIGHT}/osl/dlPrincipled"),
+                    nsi::color!("i_color", &color),
+                    nsi::float!("roughness", 0.5),
+                ],
+            );
+        }
+    });
+
+    let batch = time(|| {
+        set_attribute_batch(
+            &ctx,
+            &handles,
+            &[
+                nsi::string!("shaderfilename", "${DELIGHT}/osl/dlPrincipled"),
+                nsi::color!("i_color", &color),
+                nsi::float!("roughness", 0.5),
+            ],
+        );
+    });
+
+    println!("{} nodes", NODES);
+    println!("set_attribute() per node: {:?}", naive);
+    println!("set_attribute_batch():    {:?}", batch);
+}
@@ -0,0 +1,54 @@
+//! Renders a sphere to `progress.exr` and shows a progress bar while
+//! doing so.
+//!
+//! This needs 3Delight to be installed. Run with:
+//! ```text
+//! cargo run --example progress --features output
+//! ```
+use nsi_3delight::*;
+
+fn main() -> Result<(), NsiHelperError> {
+    let ctx = nsi::Context::new(&[]).expect("Could not create ɴsɪ context.");
+
+    // Camera.
+    let camera_transform = look_at(
+        &ctx,
+        None,
+        [0.0, 0.0, 5.0],
+        [0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+    );
+    ctx.append(".root", None, camera_transform.as_str());
+    let CameraHandles { camera, .. } =
+        perspective_camera(&ctx, None, 35.0, camera_transform.as_str(), &[]);
+
+    // Output.
+    let screen = screen(&ctx, None, camera.as_str(), (1920, 1080), 64, &[]);
+    let beauty = output_layer_aov(&ctx, None, Aov::Beauty, screen.as_str(), &[]);
+    output_driver_exr(&ctx, None, beauty.as_str(), "progress.exr", &[]);
+
+    // Progress.
+    let progress_bar = indicatif::ProgressBar::new(100);
+    {
+        let progress_bar = progress_bar.clone();
+        set_progress_callback(&ctx, beauty.as_str(), move |progress| {
+            progress_bar.set_position((100.0 * progress) as _);
+        });
+    }
+
+    // Scene.
+    let sphere = sphere(&ctx, None, 1.0, false, ".root", &[]);
+    assign_material(
+        &ctx,
+        sphere.as_str(),
+        glass_material(&ctx, None, [1.0, 1.0, 1.0], 1.5, 0.0, false, &[]).as_str(),
+    );
+
+    let environment = environment_sky(&ctx, None, None, None, None, None, &[])?;
+    ctx.append(".root", None, environment.transform.as_str());
+
+    render(&ctx, RenderAction::StartBlocking);
+    progress_bar.finish();
+
+    Ok(())
+}
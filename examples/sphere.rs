@@ -0,0 +1,53 @@
+//! Renders a sphere on a ground plane to `sphere.exr`.
+//!
+//! This needs 3Delight to be installed. Run with:
+//! ```text
+//! cargo run --example sphere
+//! ```
+use nsi_3delight::*;
+
+fn main() -> Result<(), NsiHelperError> {
+    let ctx = nsi::Context::new(&[]).expect("Could not create ɴsɪ context.");
+
+    // Camera.
+    let camera_transform = look_at(
+        &ctx,
+        None,
+        [0.0, 1.5, 5.0],
+        [0.0, 0.75, 0.0],
+        [0.0, 1.0, 0.0],
+    );
+    ctx.append(".root", None, camera_transform.as_str());
+    let CameraHandles { camera, .. } =
+        perspective_camera(&ctx, None, 35.0, camera_transform.as_str(), &[]);
+
+    // Output.
+    let screen = screen(&ctx, None, camera.as_str(), (640, 480), 16, &[]);
+    let beauty = output_layer_aov(&ctx, None, Aov::Beauty, screen.as_str(), &[]);
+    output_driver_exr(&ctx, None, beauty.as_str(), "sphere.exr", &[]);
+
+    // Geometry.
+    let sphere_transform = translation(&ctx, None, [0.0, 0.75, 0.0]);
+    ctx.append(".root", None, sphere_transform.as_str());
+    let sphere = sphere(&ctx, None, 0.75, false, sphere_transform.as_str(), &[]);
+    assign_material(
+        &ctx,
+        sphere.as_str(),
+        principled_material(&ctx, None, [0.8, 0.1, 0.1], 0.3, 0.0, None, &[]).as_str(),
+    );
+
+    let ground = ground_plane(&ctx, None, None, ".root", &[]);
+    assign_material(
+        &ctx,
+        ground.as_str(),
+        principled_material(&ctx, None, [0.18, 0.18, 0.18], 0.8, 0.0, None, &[]).as_str(),
+    );
+
+    // Lighting.
+    let environment = environment_constant(&ctx, None, [1.0, 1.0, 1.0], 1.0, None, &[])?;
+    ctx.append(".root", None, environment.transform.as_str());
+
+    render(&ctx, RenderAction::StartBlocking);
+
+    Ok(())
+}
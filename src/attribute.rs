@@ -0,0 +1,101 @@
+//! Shortcuts for setting attributes.
+
+/// Sets the same attributes on many nodes.
+///
+/// # Arguments
+/// * `handles` – The nodes to set `args` on.
+///
+/// * `args` – The attributes to set. These are built once and shared
+///   by all nodes. Build them outside of any loop calling this.
+///
+/// The nodes are updated in the order of `handles`. ɴsɪ has no call
+/// that takes several handles so this is a loop over
+/// [`set_attribute()`](nsi::Context::set_attribute()). The win over
+/// calling that yourself comes from not building the `args` for each
+/// node.
+pub fn set_attribute_batch<'a>(
+    ctx: &nsi::Context<'a>,
+    handles: &[&str],
+    args: &nsi::ArgSlice<'_, 'a>,
+) {
+    if args.is_empty() {
+        return;
+    }
+
+    for handle in handles {
+        ctx.set_attribute(*handle, args);
+    }
+}
+
+/// Sets attributes at a point in time, for motion blur.
+///
+/// # Arguments
+/// * `handle` – The node to set `args` on.
+///
+/// * `time` – The time of the sample. This is on the same time line
+///   as the camera's `shutterrange`, see
+///   [`set_shutter()`](crate::set_shutter()).
+///
+/// This is the same as
+/// [`set_attribute_at_time()`](nsi::Context::set_attribute_at_time()).
+/// It exists so it can be used alongside [`set_attribute_keyframes()`].
+pub fn set_attribute_at_time<'a>(
+    ctx: &nsi::Context<'a>,
+    handle: &str,
+    time: f64,
+    args: &nsi::ArgSlice<'_, 'a>,
+) {
+    ctx.set_attribute_at_time(handle, time, args);
+}
+
+/// Sets attributes at several points in time, for motion blur.
+///
+/// # Arguments
+/// * `handle` – The node to set the attributes on.
+///
+/// * `keys` – For each sample, its time and the attributes at that
+///   time. These do not need to be sorted; the samples are set in
+///   time order.
+///
+/// 3Delight uses all samples that fall into the shutter interval and
+/// interpolates linearly between consecutive ones. Two samples – at
+/// shutter open and close – suffice for linear motion. Curved motion
+/// or fast rotation needs more.
+///
+/// All samples of an attribute must have the same number of values.
+/// E.g. a deforming mesh must keep its point count.
+pub fn set_attribute_keyframes<'a>(
+    ctx: &nsi::Context<'a>,
+    handle: &str,
+    keys: &[(f64, Vec<nsi::Arg<'_, 'a>>)],
+) {
+    for (time, args) in in_time_order(keys) {
+        ctx.set_attribute_at_time(handle, *time, args);
+    }
+}
+
+// Returns the keys sorted by their time. Keys at the same time keep
+// their order.
+fn in_time_order<T>(keys: &[(f64, T)]) -> Vec<&(f64, T)> {
+    let mut keys = keys.iter().collect::<Vec<_>>();
+    keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes_are_in_time_order() {
+        let keys = [(0.5, 'b'), (-0.25, 'a'), (1.0, 'd'), (0.5, 'c')];
+
+        assert_eq!(
+            vec!['a', 'b', 'c', 'd'],
+            in_time_order(&keys)
+                .iter()
+                .map(|(_, key)| *key)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,617 @@
+//! Shortcuts for instancing cameras.
+use nsi::toolbelt::generate_or_use_handle;
+
+/// The handles of a camera, as returned by e.g.
+/// [`perspective_camera()`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CameraHandles {
+    /// The transform the camera is appended to.
+    pub transform: String,
+    /// The camera. Connect a [`screen()`](crate::screen()) to this.
+    pub camera: String,
+}
+
+impl From<CameraHandles> for (String, String) {
+    #[inline]
+    fn from(handles: CameraHandles) -> Self {
+        (handles.transform, handles.camera)
+    }
+}
+
+// Creates a camera node of the given type and appends it to `transform`.
+fn camera<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    node_type: nsi::NodeType,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let camera = generate_or_use_handle(handle, Some("camera"));
+
+    ctx.create(camera.as_str(), node_type, &[]);
+    ctx.append(transform, None, camera.as_str());
+
+    if !args.is_empty() {
+        ctx.set_attribute(camera.as_str(), args);
+    }
+
+    camera
+}
+
+/// Depth of field settings of a camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthOfField {
+    /// The relative aperture of the lens.
+    pub fstop: f64,
+    /// The focal length of the lens, in scene units.
+    pub focal_length: f64,
+    /// The distance to the plane in focus, in scene units.
+    pub focal_distance: f64,
+}
+
+// Returns the attributes that enable depth of field. If `dof` is None
+// there are none.
+fn depth_of_field_attributes<'b, 'a>(dof: Option<&DepthOfField>) -> nsi::ArgVec<'b, 'a> {
+    dof.map_or_else(Vec::new, |dof| {
+        vec![
+            nsi::integer!("depthoffield.enable", 1),
+            nsi::double!("depthoffield.fstop", dof.fstop),
+            nsi::double!("depthoffield.focallength", dof.focal_length),
+            nsi::double!("depthoffield.focaldistance", dof.focal_distance),
+        ]
+    })
+}
+
+/// Creates a perspective camera.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `fov` – The vertical field of view in degrees.
+///
+/// * `transform` – The transform node the camera is appended to.
+///
+/// Returns the [`CameraHandles`] of the camera.
+///
+/// Note that a camera does not render anything on its own. It needs
+/// a `screen` node with at least one `outputlayer` connected to it.
+pub fn perspective_camera<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    fov: f32,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> CameraHandles {
+    perspective_camera_dof(ctx, handle, fov, None, transform, args)
+}
+
+/// Creates a perspective camera with optional depth of field.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `fov` – The vertical field of view in degrees.
+///
+/// * `dof` – The [`DepthOfField`] settings. If [`None`], depth of
+///   field stays disabled and none of the `depthoffield.*`
+///   attributes are set.
+///
+/// * `transform` – The transform node the camera is appended to.
+///
+/// Returns the [`CameraHandles`] of the camera.
+pub fn perspective_camera_dof<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    fov: f32,
+    dof: Option<DepthOfField>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> CameraHandles {
+    let camera = camera(ctx, handle, nsi::NodeType::PerspectiveCamera, transform, &[]);
+
+    ctx.set_attribute(camera.as_str(), &[nsi::float!("fov", fov)]);
+
+    let dof = depth_of_field_attributes(dof.as_ref());
+    if !dof.is_empty() {
+        ctx.set_attribute(camera.as_str(), &dof);
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(camera.as_str(), args);
+    }
+
+    CameraHandles {
+        transform: transform.to_string(),
+        camera,
+    }
+}
+
+/// Creates an orthographic camera.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `transform` – The transform node the camera is appended to.
+///
+/// Returns the [`CameraHandles`] of the camera.
+///
+/// Note that orthographic cameras have no `fov`; the extent of the
+/// view is solely determined by the screen window. This is the region
+/// of the camera's view plane that is rendered, as
+/// `[x_min, y_min, x_max, y_max]` in camera space. It defaults to
+/// `[-1, -1, 1, 1]`. As it is an attribute of the `screen` node, set
+/// it via the `args` of [`screen()`](crate::screen()):
+/// ```no_run
+/// # use nsi_3delight::*;
+/// # let ctx = nsi::Context::new(&[]).unwrap();
+/// let camera = orthographic_camera(&ctx, None, ".root", &[]).camera;
+///
+/// screen(
+///     &ctx,
+///     None,
+///     camera.as_str(),
+///     (1024, 512),
+///     16,
+///     &[nsi::doubles!("screenwindow", &[-2.0, -1.0, 2.0, 1.0]).array_len(2)],
+/// );
+/// ```
+pub fn orthographic_camera<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> CameraHandles {
+    let camera = camera(ctx, handle, nsi::NodeType::OrthographicCamera, transform, args);
+
+    CameraHandles {
+        transform: transform.to_string(),
+        camera,
+    }
+}
+
+/// The projection used by a [`fisheye_camera()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    /// The distance from the image center is proportional to the
+    /// angle from the view axis.
+    Equidistant,
+    /// Preserves relative areas.
+    Equisolid,
+    /// Projects the hemisphere orthographically onto the image plane.
+    Orthographic,
+    /// Preserves angles (conformal).
+    Stereographic,
+}
+
+impl From<FisheyeMapping> for Vec<u8> {
+    #[inline]
+    fn from(mapping: FisheyeMapping) -> Self {
+        match mapping {
+            FisheyeMapping::Equidistant => b"equidistant".to_vec(),
+            FisheyeMapping::Equisolid => b"equisolid".to_vec(),
+            FisheyeMapping::Orthographic => b"orthographic".to_vec(),
+            FisheyeMapping::Stereographic => b"stereographic".to_vec(),
+        }
+    }
+}
+
+/// Creates a fisheye camera.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `fov` – The field of view in degrees. Use `180` for a full
+///   hemisphere (e.g. for fulldome work).
+///
+/// * `mapping` – How directions are mapped onto the image plane.
+///
+/// * `transform` – The transform node the camera is appended to.
+///
+/// Returns the [`CameraHandles`] of the camera.
+pub fn fisheye_camera<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    fov: f32,
+    mapping: FisheyeMapping,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> CameraHandles {
+    let camera = camera(ctx, handle, nsi::NodeType::FisheyeCamera, transform, &[]);
+
+    ctx.set_attribute(
+        camera.as_str(),
+        &[
+            nsi::float!("fov", fov),
+            nsi::string!("mapping", mapping),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(camera.as_str(), args);
+    }
+
+    CameraHandles {
+        transform: transform.to_string(),
+        camera,
+    }
+}
+
+/// Creates a cylindrical (panoramic) camera.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `horizontal_fov` – The horizontal field of view in degrees (sets
+///   `horizontalfov`). Use `360` for a full panorama.
+///
+/// * `vertical_fov` – The vertical field of view in degrees (sets
+///   `fov`).
+///
+/// * `transform` – The transform node the camera is appended to.
+///
+/// Returns the [`CameraHandles`] of the camera.
+///
+/// For stereo panoramas pass e.g. the `eyeoffset` attribute via
+/// `args`.
+pub fn cylindrical_camera<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    horizontal_fov: f32,
+    vertical_fov: f32,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> CameraHandles {
+    let camera = camera(ctx, handle, nsi::NodeType::CylindricalCamera, transform, &[]);
+
+    ctx.set_attribute(
+        camera.as_str(),
+        &[
+            nsi::float!("horizontalfov", horizontal_fov),
+            nsi::float!("fov", vertical_fov),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(camera.as_str(), args);
+    }
+
+    CameraHandles {
+        transform: transform.to_string(),
+        camera,
+    }
+}
+
+/// Creates a spherical (latitude-longitude) camera.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `transform` – The transform node the camera is appended to.
+///
+/// Returns the [`CameraHandles`] of the camera.
+///
+/// The camera always covers the full sphere so there is no field of
+/// view to set. For stereo renders pass e.g. the `eyeoffset`
+/// attribute via `args`.
+pub fn spherical_camera<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> CameraHandles {
+    let camera = camera(ctx, handle, nsi::NodeType::SphericalCamera, transform, args);
+
+    CameraHandles {
+        transform: transform.to_string(),
+        camera,
+    }
+}
+
+/// Sets the shutter interval of a camera for motion blur.
+///
+/// # Arguments
+/// * `camera` – The handle of a camera, e.g. as returned by
+///   [`perspective_camera()`].
+///
+/// * `open`, `close` – The times at which the shutter opens and
+///   closes. These are on the same time line as the time samples
+///   given to [`set_attribute_at_time()`](nsi::Context::set_attribute_at_time())
+///   – usually the frame time ± half the shutter angle.
+///
+/// * `opening` – The shutter efficiency curve (`shutteropening`), as
+///   two normalized times in `0..1`. The shutter is fully open in
+///   between these and ramps linearly outside. If [`None`], the
+///   shutter opens and closes instantly.
+///
+/// # Errors
+/// If `open` or `close` is not finite or `open` is greater than
+/// `close`.
+pub fn set_shutter(
+    ctx: &nsi::Context,
+    camera: &str,
+    open: f64,
+    close: f64,
+    opening: Option<[f64; 2]>,
+) -> Result<(), crate::NsiHelperError> {
+    crate::check_finite("open", open)?;
+    crate::check_finite("close", close)?;
+    if close < open {
+        return Err(crate::NsiHelperError::OutOfRange("close"));
+    }
+
+    ctx.set_attribute(
+        camera,
+        &[nsi::doubles!("shutterrange", &[open, close]).array_len(2)],
+    );
+
+    if let Some(opening) = opening {
+        ctx.set_attribute(
+            camera,
+            &[nsi::doubles!("shutteropening", &opening).array_len(2)],
+        );
+    }
+
+    Ok(())
+}
+
+/// The handles of a stereo camera rig, as returned by
+/// [`stereo_camera()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StereoHandles {
+    /// The transform both eyes are appended to.
+    pub rig: String,
+    /// The camera of the left eye.
+    pub left: String,
+    /// The camera of the right eye.
+    pub right: String,
+}
+
+// Returns the transform of an eye of a stereo_camera() rig. `sign` is
+// -1 for the left eye and 1 for the right one.
+//
+// The left eye sits at -X and turns towards +X; the right one is
+// mirrored.
+fn stereo_eye_matrix(interocular: f64, convergence: Option<f64>, sign: f64) -> [f64; 16] {
+    let toe_in = convergence.map_or(0.0, |convergence| {
+        (0.5 * interocular).atan2(convergence).to_degrees()
+    });
+
+    crate::math::multiply(
+        &crate::math::rotation(sign * toe_in, [0.0, 1.0, 0.0]),
+        &crate::math::translation([sign * 0.5 * interocular, 0.0, 0.0]),
+    )
+}
+
+/// Creates a pair of perspective cameras for stereo renders.
+///
+/// If `handle` is [`None`] a random handle is generated. This is the
+/// handle of the rig transform. The cameras get the handles
+/// `<rig>_left` and `<rig>_right` and each sits under its own
+/// `<rig>_left_eye` or `<rig>_right_eye` transform.
+///
+/// # Arguments
+/// * `fov` – The vertical field of view in degrees, of both eyes.
+///
+/// * `interocular` – The distance between the eyes, in scene units.
+///   Each eye is offset by half of this along the rig's X axis.
+///
+/// * `convergence` – The distance, in front of the rig, at which the
+///   eyes' view axes cross. The cameras are rotated around Y (toed-in)
+///   to achieve this. If [`None`], the cameras are parallel.
+///
+/// * `transform` – The transform node the rig is appended to.
+///
+/// Use `args` to set attributes on both cameras.
+///
+/// Each eye needs its own [`screen()`](crate::screen()) with its own
+/// output layers and drivers.
+///
+/// # Errors
+/// If `handle` is invalid, `interocular` is not finite or
+/// `convergence` is not a positive number.
+#[allow(clippy::too_many_arguments)]
+pub fn stereo_camera<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    fov: f32,
+    interocular: f64,
+    convergence: Option<f64>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> Result<StereoHandles, crate::NsiHelperError> {
+    crate::check_handle(handle)?;
+    crate::check_finite("interocular", interocular)?;
+    if let Some(convergence) = convergence {
+        if convergence.is_nan() || convergence <= 0.0 {
+            return Err(crate::NsiHelperError::OutOfRange("convergence"));
+        }
+    }
+
+    let rig = crate::transform_matrix(
+        ctx,
+        Some(generate_or_use_handle(handle, Some("stereo_rig")).as_str()),
+        crate::math::IDENTITY,
+    );
+    ctx.append(transform, None, rig.as_str());
+
+    let eye = |side: &str, sign: f64| {
+        let eye = crate::transform_matrix(
+            ctx,
+            Some(format!("{}_{}_eye", rig, side).as_str()),
+            stereo_eye_matrix(interocular, convergence, sign),
+        );
+        ctx.append(rig.as_str(), None, eye.as_str());
+
+        perspective_camera(
+            ctx,
+            Some(format!("{}_{}", rig, side).as_str()),
+            fov,
+            eye.as_str(),
+            args,
+        )
+        .camera
+    };
+
+    let left = eye("left", -1.0);
+    let right = eye("right", 1.0);
+
+    Ok(StereoHandles { rig, left, right })
+}
+
+/// Sets the shape of a camera's aperture for polygonal bokeh.
+///
+/// This only has an effect when depth of field is enabled, e.g. via
+/// [`perspective_camera_dof()`].
+///
+/// # Arguments
+/// * `camera` – The handle of a camera.
+///
+/// * `blades` – The number of aperture blades. `0` gives a circular
+///   aperture and disables all other settings.
+///
+/// * `rotation` – The rotation of the polygon, in degrees.
+///
+/// * `roundness` – How much the polygon's edges bulge outwards, in
+///   `0.0..=1.0`. `0` gives a polygon with straight edges, `1` a
+///   circle. The value is clamped to this range.
+///
+/// # Errors
+/// If `blades` is negative, `1` or `2` or if `rotation` or `roundness`
+/// is not finite.
+pub fn set_aperture(
+    ctx: &nsi::Context,
+    camera: &str,
+    blades: i32,
+    rotation: f64,
+    roundness: f64,
+) -> Result<(), crate::NsiHelperError> {
+    if !(0 == blades || 3 <= blades) {
+        return Err(crate::NsiHelperError::OutOfRange("blades"));
+    }
+    crate::check_finite("rotation", rotation)?;
+    crate::check_finite("roundness", roundness)?;
+
+    if 0 == blades {
+        ctx.set_attribute(camera, &[nsi::integer!("depthoffield.aperture.enable", 0)]);
+    } else {
+        ctx.set_attribute(
+            camera,
+            &[
+                nsi::integer!("depthoffield.aperture.enable", 1),
+                nsi::integer!("depthoffield.aperture.sides", blades),
+                nsi::double!("depthoffield.aperture.angle", rotation),
+                nsi::double!("depthoffield.aperture.roundness", roundness.clamp(0.0, 1.0)),
+            ],
+        );
+    }
+
+    Ok(())
+}
+
+/// Sets the range of distances from a camera that is rendered.
+///
+/// Geometry closer than `near` or farther away than `far` is not
+/// rendered. Both are in scene units, measured along the view axis.
+///
+/// The defaults of 3Delight suit most scenes. Lower `near` when
+/// foreground geometry in very small scenes is cut off. Keeping the
+/// range tight also improves the precision of depth (`z`) AOVs.
+///
+/// # Errors
+/// If `near` is not positive and finite or `far` is not greater than
+/// `near`. `far` may be infinite.
+pub fn set_clipping_planes(
+    ctx: &nsi::Context,
+    camera: &str,
+    near: f64,
+    far: f64,
+) -> Result<(), crate::NsiHelperError> {
+    crate::check_finite("near", near)?;
+    if near <= 0.0 {
+        return Err(crate::NsiHelperError::OutOfRange("near"));
+    }
+    if far.is_nan() || far <= near {
+        return Err(crate::NsiHelperError::OutOfRange("far"));
+    }
+
+    ctx.set_attribute(
+        camera,
+        &[nsi::doubles!("clippingrange", &[near, far]).array_len(2)],
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::tests::{assert_close, transform_point},
+        tests::arg_names,
+    };
+
+    // Returns where the view axis of an eye crosses the plane
+    // `z = -distance` of the rig.
+    fn view_axis_at(eye: &[f64; 16], distance: f64) -> [f64; 3] {
+        let origin = transform_point(eye, [0.0; 3]);
+        let target = transform_point(eye, [0.0, 0.0, -1.0]);
+        let t = (-distance - origin[2]) / (target[2] - origin[2]);
+
+        [
+            origin[0] + t * (target[0] - origin[0]),
+            origin[1] + t * (target[1] - origin[1]),
+            -distance,
+        ]
+    }
+
+    #[test]
+    fn fisheye_mapping_names() {
+        for &(mapping, name) in &[
+            (FisheyeMapping::Equidistant, "equidistant"),
+            (FisheyeMapping::Equisolid, "equisolid"),
+            (FisheyeMapping::Orthographic, "orthographic"),
+            (FisheyeMapping::Stereographic, "stereographic"),
+        ] {
+            assert_eq!(name.as_bytes(), Vec::<u8>::from(mapping).as_slice());
+        }
+    }
+
+    #[test]
+    fn depth_of_field_only_when_requested() {
+        assert!(depth_of_field_attributes(None).is_empty());
+
+        let dof = DepthOfField {
+            fstop: 2.8,
+            focal_length: 0.05,
+            focal_distance: 3.0,
+        };
+
+        assert_eq!(
+            vec![
+                "depthoffield.enable",
+                "depthoffield.fstop",
+                "depthoffield.focallength",
+                "depthoffield.focaldistance",
+            ],
+            arg_names(&depth_of_field_attributes(Some(&dof)))
+        );
+    }
+
+    #[test]
+    fn stereo_eyes_are_on_their_sides() {
+        let left = stereo_eye_matrix(0.065, None, -1.0);
+        let right = stereo_eye_matrix(0.065, None, 1.0);
+
+        assert_close([-0.0325, 0.0, 0.0], transform_point(&left, [0.0; 3]));
+        assert_close([0.0325, 0.0, 0.0], transform_point(&right, [0.0; 3]));
+    }
+
+    #[test]
+    fn stereo_eyes_converge() {
+        let left = stereo_eye_matrix(0.065, Some(2.0), -1.0);
+        let right = stereo_eye_matrix(0.065, Some(2.0), 1.0);
+
+        assert_close([0.0, 0.0, -2.0], view_axis_at(&left, 2.0));
+        assert_close([0.0, 0.0, -2.0], view_axis_at(&right, 2.0));
+    }
+}
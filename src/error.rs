@@ -0,0 +1,63 @@
+//! The error type of the fallible helpers.
+use std::fmt;
+
+/// An error returned by a helper when it is given invalid input.
+///
+/// Helpers check their input up front. If they return an error, no
+/// nodes have been created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NsiHelperError {
+    /// A handle is empty or contains a nul byte.
+    InvalidHandle(String),
+    /// A texture path is empty.
+    EmptyTexture,
+    /// The named argument is NaN or infinite.
+    NonFinite(&'static str),
+    /// The named argument is outside the range the helper documents.
+    OutOfRange(&'static str),
+    /// A [`ShaderGraph`](crate::ShaderGraph) refers to a node or port
+    /// that does not exist.
+    InvalidShaderGraph(String),
+    /// Render statistics could not be read or parsed.
+    #[cfg(feature = "statistics")]
+    Statistics(String),
+}
+
+impl fmt::Display for NsiHelperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NsiHelperError::InvalidHandle(handle) => write!(f, "invalid handle: {:?}", handle),
+            NsiHelperError::EmptyTexture => write!(f, "texture path is empty"),
+            NsiHelperError::NonFinite(name) => write!(f, "`{}` is not finite", name),
+            NsiHelperError::OutOfRange(name) => write!(f, "`{}` is out of range", name),
+            NsiHelperError::InvalidShaderGraph(reason) => {
+                write!(f, "invalid shader graph: {}", reason)
+            }
+            #[cfg(feature = "statistics")]
+            NsiHelperError::Statistics(reason) => {
+                write!(f, "cannot read render statistics: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NsiHelperError {}
+
+// Checks that `handle`, if given, can be passed to ɴsɪ.
+pub(crate) fn check_handle(handle: Option<&str>) -> Result<(), NsiHelperError> {
+    match handle {
+        Some(handle) if handle.is_empty() || handle.contains('\0') => {
+            Err(NsiHelperError::InvalidHandle(handle.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Checks that `value` is neither NaN nor infinite.
+pub(crate) fn check_finite(name: &'static str, value: f64) -> Result<(), NsiHelperError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(NsiHelperError::NonFinite(name))
+    }
+}
@@ -0,0 +1,192 @@
+//! Routing of renderer messages into Rust.
+use std::{
+    ffi::{c_void, CStr},
+    os::raw::{c_char, c_int},
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Mutex, PoisonError},
+};
+
+/// The severity of a message from the renderer. See [`ErrorHandler`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorLevel {
+    /// Information, e.g. statistics or progress. Plain messages
+    /// without a level are reported as this too.
+    Info,
+    /// Something is likely wrong but rendering continues, e.g. a
+    /// missing texture.
+    Warning,
+    /// Something is wrong, e.g. a file could not be read. The image
+    /// may be incomplete.
+    Error,
+}
+
+impl From<c_int> for ErrorLevel {
+    // The NSIErrMessage, NSIErrInfo, NSIErrWarning and NSIErrError
+    // levels of nsi.h.
+    #[inline]
+    fn from(level: c_int) -> Self {
+        match level {
+            2 => ErrorLevel::Warning,
+            3.. => ErrorLevel::Error,
+            _ => ErrorLevel::Info,
+        }
+    }
+}
+
+type ErrorHandlerFn = unsafe extern "C" fn(*mut c_void, c_int, c_int, *const c_char);
+
+type ErrorCallback<'a> = Mutex<Box<dyn FnMut(ErrorLevel, &str) + Send + 'a>>;
+
+/// Receives the messages of the renderer, e.g. warnings about missing
+/// textures, instead of them being printed to the terminal.
+///
+/// The renderer only accepts a handler when a context is created. So
+/// instead of being set on a context, the handler is turned into the
+/// [`args()`](ErrorHandler::args()) to create one with:
+/// ```no_run
+/// # use nsi_3delight::ErrorHandler;
+/// let handler = ErrorHandler::new(|level, message: &str| {
+///     eprintln!("{:?}: {}", level, message);
+/// });
+///
+/// let ctx = nsi::Context::new(&handler.args()).unwrap();
+/// ```
+///
+/// The context borrows the handler. It must be dropped before the
+/// handler is.
+///
+/// # Thread Safety
+/// The renderer sends messages from whatever thread produces them,
+/// often several render threads at once. The closure must thus be
+/// [`Send`]. Calls to it are serialized behind a mutex so it never
+/// runs twice at the same time. It should return quickly as it holds
+/// up the thread that sent the message.
+///
+/// If the closure panics, the panic is caught before it reaches the
+/// renderer and the message is dropped. Later messages are still
+/// passed to the closure.
+///
+/// With the `log` feature [`ErrorHandler::log()`] forwards the
+/// messages to the [`log`](https://docs.rs/log) crate.
+pub struct ErrorHandler<'a> {
+    // Boxed so its address, which the renderer holds, stays the same
+    // when the handler is moved.
+    #[allow(dead_code)]
+    callback: Box<ErrorCallback<'a>>,
+    // The renderer reads these through pointers to them.
+    function: ErrorHandlerFn,
+    userdata: *const c_void,
+}
+
+impl<'a> ErrorHandler<'a> {
+    /// Creates a handler that calls `callback` with each message and
+    /// its severity.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: FnMut(ErrorLevel, &str) + Send + 'a,
+    {
+        let callback: Box<ErrorCallback<'a>> = Box::new(Mutex::new(Box::new(callback)));
+        let userdata = &*callback as *const ErrorCallback<'a> as *const c_void;
+
+        Self {
+            callback,
+            function: error_handler,
+            userdata,
+        }
+    }
+
+    /// Returns the arguments to pass to [`nsi::Context::new()`] to
+    /// install the handler. Add any other arguments of the context to
+    /// these.
+    pub fn args(&self) -> nsi::ArgVec<'_, '_> {
+        vec![
+            nsi::reference!("errorhandler", Some(&self.function)),
+            nsi::reference!("errorhandler.data", Some(&self.userdata)),
+        ]
+    }
+}
+
+#[cfg(feature = "log")]
+impl From<ErrorLevel> for log::Level {
+    #[inline]
+    fn from(level: ErrorLevel) -> Self {
+        match level {
+            ErrorLevel::Info => log::Level::Info,
+            ErrorLevel::Warning => log::Level::Warn,
+            ErrorLevel::Error => log::Level::Error,
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl ErrorHandler<'static> {
+    /// Creates a handler that forwards each message to the
+    /// [`log`](https://docs.rs/log) crate, with the target `nsi`.
+    pub fn log() -> Self {
+        Self::new(|level, message| {
+            log::log!(target: "nsi", level.into(), "{}", message);
+        })
+    }
+}
+
+// Trampoline the renderer calls with the `userdata` of an
+// ErrorHandler.
+unsafe extern "C" fn error_handler(
+    userdata: *mut c_void,
+    level: c_int,
+    _code: c_int,
+    message: *const c_char,
+) {
+    if userdata.is_null() || message.is_null() {
+        return;
+    }
+
+    let callback = &*(userdata as *const ErrorCallback);
+    let message = CStr::from_ptr(message).to_string_lossy();
+
+    // Unwinding into the renderer is undefined behavior. A panic in
+    // the closure poisons the mutex; later messages still get through.
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let mut callback = callback.lock().unwrap_or_else(PoisonError::into_inner);
+        callback(level.into(), &message);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn error_levels() {
+        for &(level, error_level) in &[
+            (0, ErrorLevel::Info),
+            (1, ErrorLevel::Info),
+            (2, ErrorLevel::Warning),
+            (3, ErrorLevel::Error),
+        ] {
+            assert_eq!(error_level, ErrorLevel::from(level));
+        }
+    }
+
+    #[test]
+    fn panics_do_not_reach_the_renderer() {
+        let mut messages = Vec::new();
+
+        {
+            let handler = ErrorHandler::new(|level, message: &str| {
+                assert_ne!("panic", message);
+                messages.push((level, message.to_string()));
+            });
+
+            for &(level, message) in &[(3, "panic"), (2, "warning")] {
+                let message = CString::new(message).unwrap();
+                unsafe {
+                    error_handler(handler.userdata as *mut _, level, 0, message.as_ptr());
+                }
+            }
+        }
+
+        assert_eq!(vec![(ErrorLevel::Warning, "warning".to_string())], messages);
+    }
+}
@@ -0,0 +1,1153 @@
+//! Shortcuts for instancing geometry.
+use crate::math;
+use nsi::toolbelt::generate_or_use_handle;
+
+// Converts `[x, y, z, ..]` point data to the single precision ɴsɪ
+// expects.
+fn to_f32(data: &[f64]) -> Vec<f32> {
+    data.iter().map(|&value| value as f32).collect()
+}
+
+// Returns the handle of the `attributes` node of `geometry`. The node
+// is created and connected to the `geometryattributes` of `geometry`
+// the first time. Helpers setting attributes on geometry all share it
+// so calling them again updates the node instead of stacking up new
+// ones.
+pub(crate) fn geometry_attributes(ctx: &nsi::Context, geometry: &str) -> String {
+    let attributes = format!("{}_attributes", geometry);
+
+    ctx.create(attributes.as_str(), nsi::NodeType::Attributes, &[]);
+    ctx.append(geometry, Some("geometryattributes"), attributes.as_str());
+
+    attributes
+}
+
+/// Creates a polygon mesh.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `points` – The vertex positions as `[x, y, z, x, y, z, ..]`.
+///
+/// * `indices` – For each face, the indices of its vertices into
+///   `points`, in order.
+///
+/// * `face_vertex_counts` – The number of vertices of each face.
+///
+/// Returns the handle of the created mesh. Attach e.g. UVs or normals
+/// via `args`.
+///
+/// # Panics
+/// If the length of `points` is not a multiple of three or if the
+/// number of `indices` does not match the sum of the
+/// `face_vertex_counts`.
+pub fn mesh<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    points: &[f64],
+    indices: &[i32],
+    face_vertex_counts: &[i32],
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    assert!(
+        points.len() % 3 == 0,
+        "The length of the point data ({}) is not a multiple of three.",
+        points.len()
+    );
+    let vertex_count: i32 = face_vertex_counts.iter().sum();
+    assert!(
+        indices.len() == vertex_count as usize,
+        "The number of indices ({}) does not match the sum of the face vertex counts ({}).",
+        indices.len(),
+        vertex_count
+    );
+
+    let mesh = generate_or_use_handle(handle, Some("mesh"));
+
+    ctx.create(mesh.as_str(), nsi::NodeType::Mesh, &[]);
+    ctx.set_attribute(
+        mesh.as_str(),
+        &[
+            nsi::points!("P", &to_f32(points)),
+            nsi::integers!("P.indices", indices),
+            nsi::integers!("nvertices", face_vertex_counts),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(mesh.as_str(), args);
+    }
+
+    mesh
+}
+
+/// A chain of sharp edges on a [`subdivision_mesh()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crease {
+    /// The vertices along the crease, in order. Each consecutive pair
+    /// is an edge. E.g. `[0, 1, 2]` creases the edges `0-1` and
+    /// `1-2`.
+    pub indices: Vec<i32>,
+    /// How sharp the edges are. `0` is smooth; `10` and above is
+    /// infinitely sharp.
+    pub sharpness: f32,
+}
+
+// Flattens `creases` to the edge vertex pairs and the per-edge
+// sharpness ɴsɪ expects.
+fn flatten_creases(creases: &[Crease]) -> (Vec<i32>, Vec<f32>) {
+    let mut vertices = Vec::new();
+    let mut sharpness = Vec::new();
+
+    for crease in creases {
+        for edge in crease.indices.windows(2) {
+            vertices.extend_from_slice(edge);
+            sharpness.push(crease.sharpness);
+        }
+    }
+
+    (vertices, sharpness)
+}
+
+/// Creates a Catmull-Clark subdivision surface.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `points`, `indices`, `face_vertex_counts` – The control mesh. See
+///   [`mesh()`].
+///
+/// * `creases` – Edges that are sharper than the surrounding surface.
+///   May be empty.
+///
+/// Returns the handle of the created mesh.
+///
+/// # Panics
+/// In addition to the cases [`mesh()`] panics on, if a crease has
+/// fewer than two `indices`.
+pub fn subdivision_mesh<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    points: &[f64],
+    indices: &[i32],
+    face_vertex_counts: &[i32],
+    creases: &[Crease],
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    for crease in creases {
+        assert!(
+            crease.indices.len() >= 2,
+            "A crease needs at least two vertices but has {}.",
+            crease.indices.len()
+        );
+    }
+
+    let mesh = mesh(ctx, handle, points, indices, face_vertex_counts, &[]);
+
+    ctx.set_attribute(
+        mesh.as_str(),
+        &[nsi::string!("subdivision.scheme", "catmull-clark")],
+    );
+
+    if !creases.is_empty() {
+        let (crease_vertices, crease_sharpness) = flatten_creases(creases);
+
+        ctx.set_attribute(
+            mesh.as_str(),
+            &[
+                nsi::integers!("subdivision.creasevertices", &crease_vertices),
+                nsi::floats!("subdivision.creasesharpness", &crease_sharpness),
+            ],
+        );
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(mesh.as_str(), args);
+    }
+
+    mesh
+}
+
+/// The subdivision scheme of a mesh. See [`set_subdivision()`].
+///
+/// The scheme maps to the `subdivision.scheme` of the mesh:
+/// ```
+/// # use nsi_3delight::SubdivScheme;
+/// assert_eq!(b"catmull-clark".to_vec(), Vec::<u8>::from(SubdivScheme::CatmullClark));
+/// assert_eq!(b"loop".to_vec(), Vec::<u8>::from(SubdivScheme::Loop));
+/// assert!(Vec::<u8>::from(SubdivScheme::None).is_empty());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubdivScheme {
+    /// For meshes made of quads. This is what [`subdivision_mesh()`]
+    /// uses.
+    CatmullClark,
+    /// For meshes made of triangles.
+    Loop,
+    /// No subdivision; the mesh is rendered as polygons.
+    None,
+}
+
+impl From<SubdivScheme> for Vec<u8> {
+    #[inline]
+    fn from(scheme: SubdivScheme) -> Self {
+        match scheme {
+            SubdivScheme::CatmullClark => b"catmull-clark".to_vec(),
+            SubdivScheme::Loop => b"loop".to_vec(),
+            SubdivScheme::None => Vec::new(),
+        }
+    }
+}
+
+/// Turns an existing mesh into a subdivision surface or back into a
+/// polygon mesh.
+///
+/// # Arguments
+/// * `mesh` – The handle of the mesh, e.g. as returned by [`mesh()`].
+///
+/// * `scheme` – The subdivision scheme. [`SubdivScheme::None`]
+///   reverts the mesh to polygons by deleting the `subdivision.*`
+///   attributes; `adaptive` and `level` are then ignored.
+///
+/// * `adaptive` – If set, the renderer decides how finely to dice
+///   each face from its size on screen. `level` is then ignored.
+///
+/// * `level` – The number of times each face is split when not dicing
+///   adaptively, set as `subdivision.level`. Negative values are
+///   clamped to `0`. If [`None`] or when dicing adaptively, a level set
+///   before is deleted and the renderer's default is used.
+///
+/// Adaptive dicing follows the resolution of the image and how close
+/// the camera is, so it rarely needs tuning. It uses the same global
+/// quality settings as the rest of the scene, see e.g.
+/// [`set_quality()`](crate::set_quality()). A fixed `level` ignores
+/// these and dices every face the same. Each level quadruples the
+/// number of faces.
+pub fn set_subdivision(
+    ctx: &nsi::Context,
+    mesh: &str,
+    scheme: SubdivScheme,
+    adaptive: bool,
+    level: Option<i32>,
+) {
+    let (attributes, deleted) = subdivision_attributes(scheme, adaptive, level);
+
+    for name in deleted {
+        ctx.delete_attribute(mesh, name);
+    }
+
+    if !attributes.is_empty() {
+        ctx.set_attribute(mesh, &attributes);
+    }
+}
+
+// Returns the attributes set_subdivision() sets and the names of the
+// ones it deletes so the renderer falls back to its defaults.
+fn subdivision_attributes<'b, 'a>(
+    scheme: SubdivScheme,
+    adaptive: bool,
+    level: Option<i32>,
+) -> (nsi::ArgVec<'b, 'a>, Vec<&'static str>) {
+    if SubdivScheme::None == scheme {
+        return (
+            Vec::new(),
+            vec![
+                "subdivision.scheme",
+                "subdivision.adaptive",
+                "subdivision.level",
+            ],
+        );
+    }
+
+    let mut attributes = vec![
+        nsi::string!("subdivision.scheme", scheme),
+        nsi::integer!("subdivision.adaptive", adaptive as _),
+    ];
+
+    match (adaptive, level) {
+        (false, Some(level)) => {
+            attributes.push(nsi::integer!("subdivision.level", level.max(0)));
+            (attributes, Vec::new())
+        }
+        _ => (attributes, vec!["subdivision.level"]),
+    }
+}
+
+/// Creates a sphere.
+///
+/// The sphere is centered at the origin of `transform`. It is exact
+/// (it is not tessellated).
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `radius` – The radius of the sphere.
+///
+/// * `invert_normals` – The sphere's normals point outwards. Set this
+///   to flip them, e.g. to render from inside the sphere.
+///
+///   This mirrors the sphere through a transform with a scale of `-1`
+///   which is inserted between `transform` and the sphere.
+///
+/// * `transform` – The transform node the sphere is appended to.
+///
+/// Returns the handle of the created geometry.
+pub fn sphere<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    radius: f64,
+    invert_normals: bool,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let sphere = generate_or_use_handle(handle, Some("sphere"));
+
+    // A particle without a normal is rendered as a sphere.
+    ctx.create(sphere.as_str(), nsi::NodeType::Particles, &[]);
+    ctx.set_attribute(
+        sphere.as_str(),
+        &[
+            nsi::points!("P", &[0.0, 0.0, 0.0]),
+            nsi::floats!("width", &[2.0 * radius as f32]),
+        ],
+    );
+
+    if invert_normals {
+        let mirror = crate::transform_matrix(
+            ctx,
+            Some(&(sphere.clone() + "_invert")),
+            math::scale([-1.0; 3]),
+        );
+        ctx.append(transform, None, mirror.as_str());
+        ctx.append(mirror.as_str(), None, sphere.as_str());
+    } else {
+        ctx.append(transform, None, sphere.as_str());
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(sphere.as_str(), args);
+    }
+
+    sphere
+}
+
+/// Creates a ground plane.
+///
+/// The plane lies in the X-Z plane of `transform`, at `Y = 0`, and
+/// faces up (+Y).
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `size` – The edge length of the square ground, centered at the
+///   origin. If [`None`], the ground is infinite.
+///
+///   An infinite ground is a `plane` node which faces +Z by
+///   definition. It is rotated into place by a transform which is
+///   inserted between `transform` and the plane.
+///
+/// * `transform` – The transform node the plane is appended to.
+///
+/// Returns the handle of the created geometry. To e.g. make the
+/// ground a shadow catcher, attach the resp. attributes via `args`.
+pub fn ground_plane<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    size: Option<f64>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let ground = generate_or_use_handle(handle, Some("ground"));
+
+    match size {
+        Some(size) => {
+            let s = 0.5 * size as f32;
+
+            // Wound so the front face points along +Y.
+            ctx.create(ground.as_str(), nsi::NodeType::Mesh, &[]);
+            ctx.set_attribute(
+                ground.as_str(),
+                &[
+                    nsi::points!("P", &[-s, 0.0, -s, -s, 0.0, s, s, 0.0, s, s, 0.0, -s]),
+                    nsi::integers!("nvertices", &[4]),
+                ],
+            );
+            ctx.append(transform, None, ground.as_str());
+        }
+        None => {
+            ctx.create(ground.as_str(), nsi::NodeType::Plane, &[]);
+
+            // Rotate +Z to +Y.
+            let orientation = crate::transform_matrix(
+                ctx,
+                Some(&(ground.clone() + "_orientation")),
+                math::rotation(-90.0, [1.0, 0.0, 0.0]),
+            );
+            ctx.append(transform, None, orientation.as_str());
+            ctx.append(orientation.as_str(), None, ground.as_str());
+        }
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(ground.as_str(), args);
+    }
+
+    ground
+}
+
+/// The interpolation of the vertices of [`curves()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveBasis {
+    /// Straight segments between the vertices.
+    Linear,
+    /// A cubic B-spline. The curve does not pass through its vertices.
+    BSpline,
+    /// A cubic Catmull-Rom spline. The curve passes through all its
+    /// vertices except the first and the last one.
+    CatmullRom,
+}
+
+impl From<CurveBasis> for Vec<u8> {
+    #[inline]
+    fn from(basis: CurveBasis) -> Self {
+        match basis {
+            CurveBasis::Linear => b"linear".to_vec(),
+            CurveBasis::BSpline => b"b-spline".to_vec(),
+            CurveBasis::CatmullRom => b"catmull-rom".to_vec(),
+        }
+    }
+}
+
+/// Creates a set of curves, e.g. hair or fur.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `points` – The vertex positions of all curves as
+///   `[x, y, z, x, y, z, ..]`.
+///
+/// * `vertices_per_curve` – The number of vertices of each curve.
+///
+/// * `width` – The constant width of the curves. To vary the width
+///   along the curves pass a `width` array with one value per vertex
+///   via `args`.
+///
+/// * `basis` – How the curves interpolate their vertices.
+///
+/// Returns the handle of the created curves.
+///
+/// # Panics
+/// If the length of `points` is not a multiple of three or if the
+/// number of points does not match the sum of the
+/// `vertices_per_curve`.
+pub fn curves<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    points: &[f64],
+    vertices_per_curve: &[i32],
+    width: f32,
+    basis: CurveBasis,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    assert!(
+        points.len() % 3 == 0,
+        "The length of the point data ({}) is not a multiple of three.",
+        points.len()
+    );
+    let vertex_count: i32 = vertices_per_curve.iter().sum();
+    assert!(
+        points.len() / 3 == vertex_count as usize,
+        "The number of points ({}) does not match the sum of the vertices per curve ({}).",
+        points.len() / 3,
+        vertex_count
+    );
+
+    let curves = generate_or_use_handle(handle, Some("curves"));
+
+    ctx.create(curves.as_str(), nsi::NodeType::Curves, &[]);
+    ctx.set_attribute(
+        curves.as_str(),
+        &[
+            nsi::points!("P", &to_f32(points)),
+            nsi::integers!("nvertices", vertices_per_curve),
+            nsi::float!("width", width),
+            nsi::string!("basis", basis),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(curves.as_str(), args);
+    }
+
+    curves
+}
+
+/// The width of the particles created by [`points()`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PointWidth {
+    /// All particles have the same width.
+    Constant(f32),
+    /// One width per particle.
+    PerPoint(Vec<f32>),
+}
+
+/// Creates a set of particles, e.g. a point cloud.
+///
+/// Each particle is rendered as a sphere. If an `N` attribute is
+/// given via `args`, particles are rendered as disks facing along
+/// their normal instead.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `positions` – The particle positions as `[x, y, z, x, y, z, ..]`.
+///
+/// * `width` – The diameter of the particles.
+///
+/// Returns the handle of the created particles. Attach e.g. a
+/// `velocity` or `id` attribute via `args`.
+///
+/// # Panics
+/// If the length of `positions` is not a multiple of three or if the
+/// number of [`PointWidth::PerPoint`] widths does not match the
+/// number of particles.
+pub fn points<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    positions: &[f64],
+    width: PointWidth,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    assert!(
+        positions.len() % 3 == 0,
+        "The length of the position data ({}) is not a multiple of three.",
+        positions.len()
+    );
+
+    let widths = match width {
+        PointWidth::Constant(width) => vec![width; positions.len() / 3],
+        PointWidth::PerPoint(widths) => {
+            assert!(
+                widths.len() == positions.len() / 3,
+                "The number of widths ({}) does not match the number of points ({}).",
+                widths.len(),
+                positions.len() / 3
+            );
+            widths
+        }
+    };
+
+    let particles = generate_or_use_handle(handle, Some("particles"));
+
+    ctx.create(particles.as_str(), nsi::NodeType::Particles, &[]);
+    ctx.set_attribute(
+        particles.as_str(),
+        &[
+            nsi::points!("P", &to_f32(positions)),
+            nsi::floats!("width", &widths),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(particles.as_str(), args);
+    }
+
+    particles
+}
+
+/// Creates an instancer that places copies of geometry.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `prototype_handles` – The nodes to instance, e.g. meshes or
+///   transforms with geometry below them. These are connected, in
+///   order, to the instancer's `sourcemodels`.
+///
+/// * `transforms` – The model matrix of each instance. See
+///   [`transform_matrix()`](crate::transform_matrix()) for the
+///   expected element order.
+///
+/// * `model_indices` – For each instance, the index of its prototype
+///   into `prototype_handles`. If [`None`], all instances use the
+///   first prototype.
+///
+/// Returns the handle of the created instancer.
+///
+/// # Panics
+/// If `prototype_handles` is empty, if the number of `model_indices`
+/// does not match the number of `transforms` or if a model index is
+/// out of range.
+pub fn instancer<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    prototype_handles: &[&str],
+    transforms: &[[f64; 16]],
+    model_indices: Option<&[i32]>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    assert!(!prototype_handles.is_empty(), "No prototypes to instance.");
+    if let Some(model_indices) = model_indices {
+        assert!(
+            model_indices.len() == transforms.len(),
+            "The number of model indices ({}) does not match the number of transforms ({}).",
+            model_indices.len(),
+            transforms.len()
+        );
+        if let Some(index) = model_indices
+            .iter()
+            .find(|&&index| index < 0 || prototype_handles.len() <= index as usize)
+        {
+            panic!(
+                "The model index {} is out of range for {} prototypes.",
+                index,
+                prototype_handles.len()
+            );
+        }
+    }
+
+    let instancer = generate_or_use_handle(handle, Some("instances"));
+
+    ctx.create(instancer.as_str(), nsi::NodeType::Instances, &[]);
+
+    for (index, prototype) in prototype_handles.iter().enumerate() {
+        ctx.connect(
+            *prototype,
+            "",
+            instancer.as_str(),
+            "sourcemodels",
+            &[nsi::integer!("index", index as _)],
+        );
+    }
+
+    ctx.set_attribute(
+        instancer.as_str(),
+        &[nsi::double_matrices!(
+            "transformationmatrices",
+            &transforms.concat()
+        )],
+    );
+
+    if let Some(model_indices) = model_indices {
+        ctx.set_attribute(
+            instancer.as_str(),
+            &[nsi::integers!("modelindices", model_indices)],
+        );
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(instancer.as_str(), args);
+    }
+
+    instancer
+}
+
+/// Which rays see an object. See [`set_visibility()`].
+///
+/// Fields that are [`None`] are left as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Visibility {
+    /// Camera rays, i.e. if the object shows up directly in renders.
+    pub camera: Option<bool>,
+    /// Diffuse rays, i.e. if the object bounces light onto others.
+    pub diffuse: Option<bool>,
+    /// Specular rays, i.e. if the object shows up in glossy
+    /// reflections.
+    pub specular: Option<bool>,
+    /// Reflection rays, i.e. if the object shows up in mirror-like
+    /// reflections.
+    pub reflection: Option<bool>,
+    /// Refraction rays, i.e. if the object can be seen through glass.
+    pub refraction: Option<bool>,
+    /// Shadow rays, i.e. if the object casts shadows.
+    pub shadow: Option<bool>,
+}
+
+/// Sets which rays see an object.
+///
+/// # Arguments
+/// * `geometry` – The handle of the geometry. This can also be a
+///   transform; the visibility is then inherited by all geometry
+///   below it.
+///
+/// * `visibility` – Only the fields that are [`Some`] are set as
+///   `visibility.*` attributes. E.g. to hide an object from the camera
+///   but keep its shadow use:
+///   ```
+///   # use nsi_3delight::Visibility;
+///   let visibility = Visibility {
+///       camera: Some(false),
+///       ..Default::default()
+///   };
+///   ```
+///
+/// The attributes are set on the `attributes` node of `geometry`, with
+/// the handle `<geometry>_attributes`. It is created and connected to
+/// the `geometryattributes` of `geometry` if it does not exist yet. If
+/// all fields are [`None`], nothing is set.
+pub fn set_visibility(ctx: &nsi::Context, geometry: &str, visibility: Visibility) {
+    let attributes = visibility_attributes(visibility);
+
+    if !attributes.is_empty() {
+        ctx.set_attribute(geometry_attributes(ctx, geometry).as_str(), &attributes);
+    }
+}
+
+// Returns the `visibility.*` attributes of the fields of `visibility`
+// that are set.
+fn visibility_attributes<'b, 'a>(visibility: Visibility) -> nsi::ArgVec<'b, 'a> {
+    [
+        ("visibility.camera", visibility.camera),
+        ("visibility.diffuse", visibility.diffuse),
+        ("visibility.specular", visibility.specular),
+        ("visibility.reflection", visibility.reflection),
+        ("visibility.refraction", visibility.refraction),
+        ("visibility.shadow", visibility.shadow),
+    ]
+    .iter()
+    .copied()
+    .filter_map(|(name, visible)| visible.map(|visible| nsi::integer!(name, visible as _)))
+    .collect()
+}
+
+/// Sets whether both sides of a surface are shaded.
+///
+/// # Arguments
+/// * `geometry` – The handle of the geometry or of a transform above
+///   it.
+///
+/// * `enable` – If set, back faces are shaded too, with the normal
+///   flipped towards the viewer. Otherwise they are not rendered.
+///
+/// Thin geometry without an inside, e.g. leaves or paper, needs both
+/// sides. With a translucent material, see
+/// [`principled_material()`](crate::principled_material()), light
+/// then also passes through it. As both sides of such geometry are
+/// hit by rays, this can up to double the shading cost.
+///
+/// The `doublesided` attribute is set on the `attributes` node of
+/// `geometry`, with the handle `<geometry>_attributes`. It is created
+/// and connected to the `geometryattributes` of `geometry` if it does
+/// not exist yet.
+pub fn set_double_sided(ctx: &nsi::Context, geometry: &str, enable: bool) {
+    ctx.set_attribute(
+        geometry_attributes(ctx, geometry).as_str(),
+        &[nsi::integer!("doublesided", enable as _)],
+    );
+}
+
+/// The handles of a volume, as returned by [`volume_vdb()`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VolumeHandles {
+    /// The volume geometry.
+    pub volume: String,
+    /// The volume shader, e.g. to set its parameters on.
+    pub shader: String,
+}
+
+/// Creates a volume from an OpenVDB file, e.g. smoke or fire.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `vdb_path` – The path of the `.vdb` file.
+///
+/// * `density_grid` – The name of the grid holding the density, e.g.
+///   `density`.
+///
+/// * `temperature_grid` – The name of the grid holding the
+///   temperature, e.g. `temperature`. This drives the emission of
+///   e.g. fire. If [`None`], the volume does not emit light.
+///
+/// * `transform` – The transform node the volume is appended to.
+///
+/// Returns the [`VolumeHandles`] of the volume.
+///
+/// To motion blur the volume pass e.g. `velocitygrid` (the name of a
+/// vector grid) and `velocityscale` via `args`.
+///
+/// # Panics
+/// If `density_grid` or `temperature_grid` are empty. This is checked
+/// before any nodes are created.
+pub fn volume_vdb<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    vdb_path: &str,
+    density_grid: &str,
+    temperature_grid: Option<&str>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> VolumeHandles {
+    assert!(!density_grid.is_empty(), "The density grid name is empty.");
+    assert!(
+        Some("") != temperature_grid,
+        "The temperature grid name is empty."
+    );
+
+    let volume = generate_or_use_handle(handle, Some("volume"));
+
+    ctx.create(volume.as_str(), nsi::NodeType::Volume, &[]);
+    ctx.set_attribute(
+        volume.as_str(),
+        &[
+            nsi::string!("vdbfilename", vdb_path),
+            nsi::string!("densitygrid", density_grid),
+        ],
+    );
+
+    if let Some(temperature_grid) = temperature_grid {
+        ctx.set_attribute(
+            volume.as_str(),
+            &[nsi::string!("temperaturegrid", temperature_grid)],
+        );
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(volume.as_str(), args);
+    }
+
+    ctx.append(transform, None, volume.as_str());
+
+    let shader = ctx.node(
+        None,
+        nsi::NodeType::Shader,
+        &[nsi::string!("shaderfilename", "${DELIGHT}/osl/vdbVolume")],
+    );
+
+    ctx.append(
+        volume.as_str(),
+        Some("geometryattributes"),
+        ctx.append(
+            &ctx.node(None, nsi::NodeType::Attributes, &[]),
+            Some("volumeshader"),
+            shader.as_str(),
+        )
+        .0,
+    );
+
+    VolumeHandles { volume, shader }
+}
+
+// Creates a procedural node of `procedural_type` that reads
+// `file_name` and appends it to `transform`.
+fn procedural(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    prefix: &str,
+    procedural_type: &str,
+    file_name: &str,
+    transform: &str,
+) -> String {
+    let procedural = generate_or_use_handle(handle, Some(prefix));
+
+    ctx.create(procedural.as_str(), nsi::NodeType::Procedural, &[]);
+    ctx.set_attribute(
+        procedural.as_str(),
+        &[
+            nsi::string!("type", procedural_type),
+            nsi::string!("filename", file_name),
+        ],
+    );
+
+    ctx.append(transform, None, procedural.as_str());
+
+    procedural
+}
+
+/// The library of 3Delight's Alembic procedural, as used by
+/// [`alembic()`].
+pub const ALEMBIC_PROCEDURAL: &str = "${DELIGHT}/lib/nsialembic";
+
+/// References geometry from an Alembic (`.abc`) archive.
+///
+/// The archive is not read here. It is loaded by 3Delight's Alembic
+/// procedural when the renderer needs it, which keeps large caches
+/// out of memory until then. This relies on the procedural,
+/// [`ALEMBIC_PROCEDURAL`], being installed with 3Delight.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `abc_path` – The path of the archive.
+///
+/// * `object_path` – The path of an object inside the archive, e.g.
+///   `/world/chair`, to only load it and its children. If [`None`],
+///   the whole archive is loaded.
+///
+/// * `transform` – The transform node the procedural is appended to.
+///
+/// Returns the handle of the created `procedural` node.
+///
+/// Animated caches are sampled at the times of the camera's shutter,
+/// see [`set_shutter()`](crate::set_shutter()). These are on the
+/// time line of the archive, in seconds. To render frame `n` of a
+/// cache at 24 fps open the shutter around `n / 24`.
+///
+/// # Panics
+/// If `abc_path` is empty.
+pub fn alembic<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    abc_path: &str,
+    object_path: Option<&str>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    assert!(!abc_path.is_empty(), "The Alembic archive path is empty.");
+
+    let procedural = procedural(
+        ctx,
+        handle,
+        "alembic",
+        "dynamiclibrary",
+        ALEMBIC_PROCEDURAL,
+        transform,
+    );
+    ctx.set_attribute(
+        procedural.as_str(),
+        &[nsi::string!("alembicfile", abc_path)],
+    );
+
+    if let Some(object_path) = object_path {
+        ctx.set_attribute(
+            procedural.as_str(),
+            &[nsi::string!("objectpath", object_path)],
+        );
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(procedural.as_str(), args);
+    }
+
+    procedural
+}
+
+/// The library of 3Delight's USD procedural, as used by
+/// [`usd_reference()`].
+pub const USD_PROCEDURAL: &str = "${DELIGHT}/lib/nsiusd";
+
+/// References a USD stage or a prim on it.
+///
+/// Like with [`alembic()`] the stage is loaded by a procedural when
+/// the renderer needs it. This relies on 3Delight's USD procedural,
+/// [`USD_PROCEDURAL`], being installed.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `usd_path` – The path of the root layer of the stage, e.g. a
+///   `.usd`, `.usda` or `.usdc` file.
+///
+/// * `prim_path` – The path of a prim on the stage, e.g.
+///   `/World/Set`, to only load it and its descendants. If [`None`],
+///   the whole stage is loaded.
+///
+/// * `transform` – The transform node the procedural is appended to.
+///
+/// Use `args` to pass further parameters to the procedural, e.g.
+/// which purposes (`render`, `proxy`, `guide`) to load or variant
+/// selections. See the documentation of the procedural for their
+/// names.
+///
+/// Returns the handle of the created `procedural` node.
+///
+/// Animated stages are sampled at the times of the camera's shutter,
+/// see [`set_shutter()`](crate::set_shutter()). Open the shutter
+/// around the time of the frame to render, in seconds. The procedural
+/// converts these to the stage's time codes.
+///
+/// # Panics
+/// If `usd_path` is empty.
+pub fn usd_reference<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    usd_path: &str,
+    prim_path: Option<&str>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    assert!(!usd_path.is_empty(), "The USD stage path is empty.");
+
+    let procedural = procedural(
+        ctx,
+        handle,
+        "usd",
+        "dynamiclibrary",
+        USD_PROCEDURAL,
+        transform,
+    );
+    ctx.set_attribute(procedural.as_str(), &[nsi::string!("usdfile", usd_path)]);
+
+    if let Some(prim_path) = prim_path {
+        ctx.set_attribute(procedural.as_str(), &[nsi::string!("primpath", prim_path)]);
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(procedural.as_str(), args);
+    }
+
+    procedural
+}
+
+/// Includes a scene fragment from an ɴsɪ stream file.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `nsi_path` – The path of the `.nsi` (binary) or `.nsia` (ASCII)
+///   file. This may contain tokens, e.g. `${DELIGHT}` or other
+///   `${...}` environment variables, which the renderer expands.
+///
+/// * `transform` – The transform node the procedural is appended to.
+///
+/// Returns the handle of the created `procedural` node.
+///
+/// The nodes the stream creates live in a namespace of their own, below
+/// the procedural. What the stream connects to `.root` ends up under
+/// the procedural, and so under `transform`. Handles in the stream do
+/// thus not collide with the ones of the scene, or of other includes of
+/// the same file, which makes the fragment reusable.
+///
+/// # Panics
+/// If `nsi_path` is empty.
+pub fn include_nsi<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    nsi_path: &str,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    assert!(!nsi_path.is_empty(), "The ɴsɪ stream path is empty.");
+
+    let procedural = procedural(ctx, handle, "include", "apistream", nsi_path, transform);
+
+    if !args.is_empty() {
+        ctx.set_attribute(procedural.as_str(), args);
+    }
+
+    procedural
+}
+
+/// Runs a procedural from a dynamic library, e.g. a plug-in that
+/// generates geometry.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `dso_path` – The path of the library (`.so`, `.dylib` or
+///   `.dll`). It must implement the ɴsɪ procedural entry points, i.e.
+///   export `NSIProceduralLoad()`.
+///
+/// * `parameters` – Set on the `procedural` node as they are. The
+///   renderer passes them on to the library verbatim; this crate does
+///   not look at them.
+///
+/// * `transform` – The transform node the procedural is appended to.
+///
+/// Returns the handle of the created `procedural` node. As with
+/// [`include_nsi()`], what the library creates lives in a namespace
+/// of its own below it.
+///
+/// # Panics
+/// If `dso_path` is empty.
+pub fn procedural_dso<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    dso_path: &str,
+    parameters: &nsi::ArgSlice<'b, 'a>,
+    transform: &str,
+) -> String {
+    assert!(!dso_path.is_empty(), "The procedural library path is empty.");
+
+    let procedural = procedural(ctx, handle, "procedural", "dynamiclibrary", dso_path, transform);
+
+    if !parameters.is_empty() {
+        ctx.set_attribute(procedural.as_str(), parameters);
+    }
+
+    procedural
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creases_are_flattened_to_edges() {
+        let (vertices, sharpness) = flatten_creases(&[
+            Crease {
+                indices: vec![0, 1, 2],
+                sharpness: 2.0,
+            },
+            Crease {
+                indices: vec![5, 4],
+                sharpness: 10.0,
+            },
+        ]);
+
+        assert_eq!(vec![0, 1, 1, 2, 5, 4], vertices);
+        assert_eq!(vec![2.0, 2.0, 10.0], sharpness);
+    }
+
+    #[test]
+    fn subdivision_attributes_per_scheme() {
+        use crate::tests::arg_names;
+
+        let names = |scheme, adaptive, level| {
+            let (attributes, deleted) = subdivision_attributes(scheme, adaptive, level);
+            (arg_names(&attributes), deleted)
+        };
+
+        assert_eq!(
+            (
+                Vec::<String>::new(),
+                vec![
+                    "subdivision.scheme",
+                    "subdivision.adaptive",
+                    "subdivision.level"
+                ]
+            ),
+            names(SubdivScheme::None, false, Some(2))
+        );
+        assert_eq!(
+            (
+                vec!["subdivision.scheme".to_string(), "subdivision.adaptive".to_string()],
+                vec!["subdivision.level"]
+            ),
+            names(SubdivScheme::CatmullClark, true, Some(2))
+        );
+        assert_eq!(
+            (
+                vec!["subdivision.scheme".to_string(), "subdivision.adaptive".to_string()],
+                vec!["subdivision.level"]
+            ),
+            names(SubdivScheme::Loop, false, None)
+        );
+        assert_eq!(
+            (
+                vec![
+                    "subdivision.scheme".to_string(),
+                    "subdivision.adaptive".to_string(),
+                    "subdivision.level".to_string()
+                ],
+                Vec::<&str>::new()
+            ),
+            names(SubdivScheme::Loop, false, Some(2))
+        );
+    }
+
+    #[test]
+    fn only_set_visibility_is_emitted() {
+        use crate::tests::arg_names;
+
+        assert!(visibility_attributes(Visibility::default()).is_empty());
+        assert_eq!(
+            vec!["visibility.camera", "visibility.shadow"],
+            arg_names(&visibility_attributes(Visibility {
+                camera: Some(false),
+                shadow: Some(true),
+                ..Default::default()
+            }))
+        );
+    }
+}
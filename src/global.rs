@@ -0,0 +1,374 @@
+//! Shortcuts for setting up the `.global` node and render quality.
+
+/// A render quality setting for [`set_quality()`].
+///
+/// The presets use these values:
+///
+/// | Preset    | `quality.shadingsamples` | `oversampling` | `maximumraydepth.diffuse` | `.reflection` | `.refraction` | `.hair` | `.volume` |
+/// | --------- | ------------------------:| --------------:| -------------------------:| -------------:| -------------:| -------:| ---------:|
+/// | `Preview` | 1                        | 4              | 1                         | 1             | 2             | 2       | 0         |
+/// | `Medium`  | 16                       | 16             | 2                         | 2             | 4             | 4       | 1         |
+/// | `High`    | 32                       | 32             | 3                         | 4             | 6             | 5       | 2         |
+/// | `Final`   | 64                       | 64             | 4                         | 6             | 8             | 8       | 2         |
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QualityPreset {
+    /// Fast and noisy, for look development.
+    Preview,
+    /// Good enough for client previews.
+    Medium,
+    /// For most final renders.
+    High,
+    /// For scenes with a lot of indirect light, glass or hair.
+    Final,
+}
+
+/// Sets the render quality from a preset.
+///
+/// # Arguments
+/// * `screen` – The handle of the screen, e.g. as returned by
+///   [`screen()`](crate::screen()). In ɴsɪ, the number of pixel
+///   samples is an attribute of the screen, not of `.global`.
+///
+/// * `preset` – See [`QualityPreset`] for the values used.
+pub fn set_quality(ctx: &nsi::Context, screen: &str, preset: QualityPreset) {
+    let (shading_samples, pixel_samples, [diffuse, reflection, refraction, hair, volume]) =
+        match preset {
+            QualityPreset::Preview => (1, 4, [1, 1, 2, 2, 0]),
+            QualityPreset::Medium => (16, 16, [2, 2, 4, 4, 1]),
+            QualityPreset::High => (32, 32, [3, 4, 6, 5, 2]),
+            QualityPreset::Final => (64, 64, [4, 6, 8, 8, 2]),
+        };
+
+    set_sampling(ctx, screen, shading_samples, pixel_samples);
+
+    set_ray_depth(
+        ctx,
+        RayDepth {
+            diffuse: Some(diffuse),
+            reflection: Some(reflection),
+            refraction: Some(refraction),
+            hair: Some(hair),
+            volume: Some(volume),
+            ..Default::default()
+        },
+    );
+}
+
+/// Sets the number of samples used to render the image.
+///
+/// # Arguments
+/// * `screen` – The handle of the screen.
+///
+/// * `shading_samples` – The number of samples used to compute
+///   lighting at each shading point (`quality.shadingsamples` on
+///   `.global`). Raise this to reduce noise.
+///
+/// * `pixel_samples` – The number of camera rays per pixel
+///   (`oversampling` on `screen`). Raise this to reduce aliasing and
+///   noise in depth of field and motion blur.
+pub fn set_sampling(ctx: &nsi::Context, screen: &str, shading_samples: i32, pixel_samples: i32) {
+    ctx.set_attribute(
+        ".global",
+        &[nsi::integer!("quality.shadingsamples", shading_samples)],
+    );
+    ctx.set_attribute(screen, &[nsi::integer!("oversampling", pixel_samples)]);
+}
+
+/// The maximum number of bounces of each kind of ray. See
+/// [`set_ray_depth()`].
+///
+/// Fields that are [`None`] are left as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RayDepth {
+    /// Diffuse bounces, i.e. indirect light.
+    pub diffuse: Option<i32>,
+    /// Glossy bounces.
+    pub specular: Option<i32>,
+    /// Mirror-like reflections.
+    pub reflection: Option<i32>,
+    /// Refractions, e.g. through glass.
+    pub refraction: Option<i32>,
+    /// Bounces inside hair and fur.
+    pub hair: Option<i32>,
+    /// Scattering events inside volumes.
+    pub volume: Option<i32>,
+}
+
+/// Limits how often rays bounce.
+///
+/// Lower limits render faster but scenes may look darker. E.g. glass
+/// turns black where refraction rays run out.
+///
+/// # Arguments
+/// * `depth` – Only the fields that are [`Some`] are set as
+///   `maximumraydepth.*` attributes on `.global`. E.g. to only cap
+///   refraction:
+///   ```
+///   # use nsi_3delight::RayDepth;
+///   let depth = RayDepth {
+///       refraction: Some(4),
+///       ..Default::default()
+///   };
+///   ```
+pub fn set_ray_depth(ctx: &nsi::Context, depth: RayDepth) {
+    let attributes = ray_depth_attributes(depth);
+
+    if !attributes.is_empty() {
+        ctx.set_attribute(".global", &attributes);
+    }
+}
+
+// Returns the `maximumraydepth.*` attributes of the fields of `depth`
+// that are set.
+fn ray_depth_attributes<'b, 'a>(depth: RayDepth) -> nsi::ArgVec<'b, 'a> {
+    [
+        ("maximumraydepth.diffuse", depth.diffuse),
+        ("maximumraydepth.specular", depth.specular),
+        ("maximumraydepth.reflection", depth.reflection),
+        ("maximumraydepth.refraction", depth.refraction),
+        ("maximumraydepth.hair", depth.hair),
+        ("maximumraydepth.volume", depth.volume),
+    ]
+    .iter()
+    .copied()
+    .filter_map(|(name, depth)| depth.map(|depth| nsi::integer!(name, depth)))
+    .collect()
+}
+
+/// Clamps bright samples to suppress fireflies.
+///
+/// Fireflies are isolated, overly bright pixels. They come from rare
+/// paths that hit small, bright light sources or highlights via
+/// indirect bounces.
+///
+/// # Arguments
+/// * `indirect_clamp` – The maximum value of samples of indirect
+///   light (`clampindirect` on `.global`).
+///
+/// * `max_value` – The maximum value of any sample, direct or
+///   indirect (`clampvalue` on `.global`).
+///
+/// Only the arguments that are [`Some`] are set.
+///
+/// Clamping loses energy: highlights and indirect light get darker
+/// and the image is no longer physically correct. Use values as high
+/// as possible. Unlike denoising, see
+/// [`enable_denoising()`](crate::enable_denoising()), which smooths
+/// the noise it is given after rendering, clamping changes what is
+/// rendered.
+pub fn set_clamp(ctx: &nsi::Context, indirect_clamp: Option<f32>, max_value: Option<f32>) {
+    let attributes = [("clampindirect", indirect_clamp), ("clampvalue", max_value)]
+        .iter()
+        .copied()
+        .filter_map(|(name, value)| value.map(|value| nsi::float!(name, value)))
+        .collect::<Vec<_>>();
+
+    if !attributes.is_empty() {
+        ctx.set_attribute(".global", &attributes);
+    }
+}
+
+/// Sets the seed of the renderer's sample patterns.
+///
+/// This is the `randomseed` attribute of `.global`. Renders of the
+/// same scene with the same seed, on the same version of 3Delight,
+/// have identical noise. This makes it possible to compare renders
+/// pixel by pixel, e.g. against golden images in tests.
+///
+/// For animations the same seed on every frame makes the noise stick
+/// to the screen. Use [`set_sampling_seed_for_frame()`] to vary it.
+pub fn set_sampling_seed(ctx: &nsi::Context, seed: i32) {
+    ctx.set_attribute(".global", &[nsi::integer!("randomseed", seed)]);
+}
+
+/// Sets the seed of the renderer's sample patterns for a frame of an
+/// animation.
+///
+/// The seed is `base + frame`. Each frame thus gets different noise
+/// while re-rendering a frame gives the same. See
+/// [`set_sampling_seed()`].
+pub fn set_sampling_seed_for_frame(ctx: &nsi::Context, base: i32, frame: i32) {
+    set_sampling_seed(ctx, base.wrapping_add(frame));
+}
+
+/// The order buckets are rendered in. See [`set_bucket_order()`].
+///
+/// The default is [`BucketOrder::Horizontal`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BucketOrder {
+    /// Row by row, from the top, left to right.
+    #[default]
+    Horizontal,
+    /// Column by column, from the left, top to bottom.
+    Vertical,
+    /// Row by row, from the top, alternating direction.
+    ZigZag,
+    /// In a square spiral, from the center outwards.
+    Spiral,
+    /// In rings, from the center outwards.
+    Circle,
+}
+
+impl From<BucketOrder> for Vec<u8> {
+    #[inline]
+    fn from(order: BucketOrder) -> Self {
+        match order {
+            BucketOrder::Horizontal => b"horizontal".to_vec(),
+            BucketOrder::Vertical => b"vertical".to_vec(),
+            BucketOrder::ZigZag => b"zigzag".to_vec(),
+            BucketOrder::Spiral => b"spiral".to_vec(),
+            BucketOrder::Circle => b"circle".to_vec(),
+        }
+    }
+}
+
+/// Sets the order buckets are rendered in (`bucketorder` on
+/// `.global`).
+///
+/// [`BucketOrder::Spiral`] and [`BucketOrder::Circle`] render the
+/// center of the image, where the subject usually is, first. This
+/// gives useful feedback sooner in look-dev. The order does not
+/// change the final image.
+pub fn set_bucket_order(ctx: &nsi::Context, order: BucketOrder) {
+    ctx.set_attribute(".global", &[nsi::string!("bucketorder", order)]);
+}
+
+/// The handle of the transform that [`set_scene_units()`] scales.
+pub const SCENE_UNITS: &str = "scene_units";
+
+/// The handle of the transform to append a scene to when using
+/// [`set_scene_units()`] or [`set_up_axis()`].
+///
+/// It is a child of [`SCENE_UNITS`] which is a child of `.root`.
+pub const SCENE_ROOT: &str = "scene_root";
+
+// Creates the SCENE_UNITS and SCENE_ROOT transforms, if needed, and
+// sets the matrix of `handle`, one of them. Re-creating a node is a
+// no-op so the matrix of the other one is kept.
+fn scene_transform(ctx: &nsi::Context, handle: &str, matrix: [f64; 16]) -> String {
+    ctx.create(SCENE_UNITS, nsi::NodeType::Transform, &[]);
+    ctx.create(SCENE_ROOT, nsi::NodeType::Transform, &[]);
+    ctx.append(".root", None, SCENE_UNITS);
+    ctx.append(SCENE_UNITS, None, SCENE_ROOT);
+
+    crate::transform_matrix(ctx, Some(handle), matrix);
+
+    SCENE_ROOT.to_string()
+}
+
+/// Sets the size of one scene unit.
+///
+/// ɴsɪ has no notion of units. The helpers in this crate, e.g. the
+/// default sizes of lights or the physically based sky, assume scenes
+/// are modeled in meters and Y-up. A scene that does not use either
+/// setting is rendered as if `meters_per_unit` was `1.0` and the up
+/// axis was [`UpAxis::YUp`].
+///
+/// This scales the [`SCENE_UNITS`] transform by `meters_per_unit`.
+/// E.g. for a scene modeled in centimeters pass `0.01`.
+///
+/// Returns the handle of the transform to append the scene, including
+/// cameras and lights, to: [`SCENE_ROOT`]. Environments stay connected
+/// to `.root`.
+///
+/// # Errors
+/// If `meters_per_unit` is not finite or not positive.
+pub fn set_scene_units(
+    ctx: &nsi::Context,
+    meters_per_unit: f64,
+) -> Result<String, crate::NsiHelperError> {
+    crate::check_finite("meters_per_unit", meters_per_unit)?;
+    if meters_per_unit <= 0.0 {
+        return Err(crate::NsiHelperError::OutOfRange("meters_per_unit"));
+    }
+
+    Ok(scene_transform(
+        ctx,
+        SCENE_UNITS,
+        crate::math::scale([meters_per_unit; 3]),
+    ))
+}
+
+/// Which axis of a scene points up. See [`set_up_axis()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpAxis {
+    /// The convention of ɴsɪ, 3Delight and the helpers of this crate.
+    #[default]
+    YUp,
+    /// The convention of e.g. Blender and most CAD applications.
+    ZUp,
+}
+
+impl UpAxis {
+    // The rotation that takes a scene with this up axis to Y-up.
+    pub(crate) fn matrix(self) -> [f64; 16] {
+        match self {
+            UpAxis::YUp => crate::math::IDENTITY,
+            UpAxis::ZUp => crate::math::rotation(-90.0, [1.0, 0.0, 0.0]),
+        }
+    }
+}
+
+/// Sets which axis of a scene points up.
+///
+/// The environment helpers put the horizon in the X-Z plane. For
+/// [`UpAxis::ZUp`] this rotates the [`SCENE_ROOT`] transform by -90°
+/// around X, taking +Z to +Y, so a Z-up scene appended to it sits on
+/// that horizon.
+///
+/// Returns the handle of the transform to append the scene, including
+/// cameras and lights, to: [`SCENE_ROOT`].
+///
+/// See [`set_scene_units()`] for the defaults.
+pub fn set_up_axis(ctx: &nsi::Context, axis: UpAxis) -> String {
+    scene_transform(ctx, SCENE_ROOT, axis.matrix())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::tests::{assert_close, transform_point};
+
+    #[test]
+    fn only_set_ray_depths_are_emitted() {
+        use crate::tests::arg_names;
+
+        assert!(ray_depth_attributes(RayDepth::default()).is_empty());
+        assert_eq!(
+            vec!["maximumraydepth.reflection", "maximumraydepth.volume"],
+            arg_names(&ray_depth_attributes(RayDepth {
+                reflection: Some(4),
+                volume: Some(0),
+                ..Default::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn bucket_order_names() {
+        for &(order, name) in &[
+            (BucketOrder::Horizontal, "horizontal"),
+            (BucketOrder::Vertical, "vertical"),
+            (BucketOrder::ZigZag, "zigzag"),
+            (BucketOrder::Spiral, "spiral"),
+            (BucketOrder::Circle, "circle"),
+        ] {
+            assert_eq!(name.as_bytes().to_vec(), Vec::<u8>::from(order));
+        }
+    }
+
+    #[test]
+    fn z_up_takes_z_to_y() {
+        let m = UpAxis::ZUp.matrix();
+
+        assert_close([0.0, 1.0, 0.0], transform_point(&m, [0.0, 0.0, 1.0]));
+        assert_close([0.0, 0.0, -1.0], transform_point(&m, [0.0, 1.0, 0.0]));
+        assert_close([1.0, 0.0, 0.0], transform_point(&m, [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn y_up_is_identity() {
+        assert_eq!(crate::math::IDENTITY, UpAxis::YUp.matrix());
+        assert_eq!(UpAxis::YUp, UpAxis::default());
+    }
+}
@@ -0,0 +1,67 @@
+//! The environment helpers of 0.1, deprecated. They have the
+//! signatures of 0.1 and return a `(transform, shader)` tuple instead
+//! of an [`EnvironmentHandles`](crate::EnvironmentHandles).
+//!
+//! These will be removed in the next release. To migrate, replace
+//! e.g. `let (transform, shader) = environment(..);` with
+//! `let EnvironmentHandles { transform, shader } = environment(..)?;`.
+//!
+//! Unlike the new versions, these panic on invalid input.
+#![allow(deprecated)]
+
+const INVALID_INPUT: &str = "Invalid input to environment helper";
+
+/// See [`environment()`](crate::environment()).
+#[deprecated(
+    since = "0.2.0",
+    note = "use `environment()` which returns `EnvironmentHandles`"
+)]
+pub fn environment(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    angle: Option<f64>,
+    visible: Option<bool>,
+) -> (String, String) {
+    crate::environment(ctx, handle, angle, visible)
+        .expect(INVALID_INPUT)
+        .into()
+}
+
+/// See [`environment_texture()`](crate::environment_texture()).
+#[deprecated(
+    since = "0.2.0",
+    note = "use `environment_texture()` which returns `EnvironmentHandles`"
+)]
+pub fn environment_texture<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    texture: &str,
+    angle: Option<f64>,
+    exposure: Option<f32>,
+    visible: Option<bool>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> (String, String) {
+    crate::environment_texture(
+        ctx, handle, texture, None, angle, None, exposure, None, None, visible, args,
+    )
+    .expect(INVALID_INPUT)
+    .into()
+}
+
+/// See [`environment_sky()`](crate::environment_sky()).
+#[deprecated(
+    since = "0.2.0",
+    note = "use `environment_sky()` which returns `EnvironmentHandles`"
+)]
+pub fn environment_sky<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    angle: Option<f64>,
+    exposure: Option<f32>,
+    visible: Option<bool>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> (String, String) {
+    crate::environment_sky(ctx, handle, angle, None, exposure, visible, args)
+        .expect(INVALID_INPUT)
+        .into()
+}
@@ -1,8 +1,92 @@
 //! # Nodal Scene Interface Helpers For 3Delight
 //! Shortcuts for instancing common nodes.
-use nsi;
+//!
+//! ## Cargo Features
+//! * `chrono` – Add [`sun_sky()`] and [`solar_vector()`] to place the
+//!   sun for a location on earth at a given time.
+//!
+//! * `output` – Add [`output_driver_callback()`] to receive rendered
+//!   pixels in memory.
+//!
+//! * `image` – Add [`render_to_image()`] to render into an
+//!   [`image::RgbaImage`]. This enables `output`.
+//!
+//! * `statistics` – Add [`RenderStats`] to read the statistics
+//!   [`enable_statistics()`] writes.
+//!
+//! * `log` – Add [`ErrorHandler::log()`] to forward the renderer's
+//!   messages to the [`log`](https://docs.rs/log) crate.
+use error::{check_finite, check_handle};
 use nsi::toolbelt::generate_or_use_handle;
 
+mod attribute;
+mod camera;
+mod error;
+mod error_handler;
+mod geometry;
+mod global;
+pub mod legacy;
+mod light;
+mod material;
+mod math;
+mod output;
+mod registry;
+mod render;
+mod shader_graph;
+mod statistics;
+#[cfg(feature = "chrono")]
+mod sun;
+mod transform;
+
+pub use attribute::*;
+pub use camera::*;
+pub use error::*;
+pub use error_handler::*;
+pub use geometry::*;
+pub use global::*;
+pub use light::*;
+pub use material::*;
+pub use output::*;
+pub use registry::*;
+pub use render::*;
+pub use shader_graph::*;
+pub use statistics::*;
+#[cfg(feature = "chrono")]
+pub use sun::*;
+pub use transform::*;
+
+/// The handles of the nodes created by the `environment*()` helpers
+/// and [`atmosphere()`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnvironmentHandles {
+    /// The transform that orients the environment. This is not
+    /// connected to anything; append it to the scene's root.
+    pub transform: String,
+    /// The environment's surface shader or, for [`atmosphere()`], its
+    /// volume shader.
+    pub shader: String,
+}
+
+impl From<EnvironmentHandles> for (String, String) {
+    #[inline]
+    fn from(handles: EnvironmentHandles) -> Self {
+        (handles.transform, handles.shader)
+    }
+}
+
+// Wraps `angle`, in degrees, into `0..360`.
+#[inline]
+fn normalize_angle(angle: f64) -> f64 {
+    // `rem_euclid()` can round up to exactly `360` for tiny negative
+    // angles.
+    let angle = angle.rem_euclid(360.0);
+    if angle < 360.0 {
+        angle
+    } else {
+        0.0
+    }
+}
+
 /// Creates a typical environment node.
 ///
 /// A latitutde-lungitude environment map will be aligned as-shot
@@ -12,26 +96,157 @@ use nsi::toolbelt::generate_or_use_handle;
 ///
 /// # Arguments
 /// * `angle` – In degrees; specicfies how much to rotate the
-///     environment around the Y (up) axis.
+///   environment around the Y (up) axis. This is wrapped into
+///   `0..360`, e.g. `450` becomes `90` and `-90` becomes `270`.
 ///
 /// * `visible` – If the environment is visible to the camera.
 ///
-/// Returns `handle` and the handle of the created `shader`.
+/// Returns the handles of the transform and the created `shader`.
 ///
 /// Note that the `shader` node is empty. It is up to the user
 /// to set the resp. attributes on the node or hook up an OSL
 /// network below it.
+///
+/// # Errors
+/// If `handle` is invalid or `angle` is not finite.
 pub fn environment(
     ctx: &nsi::Context,
     handle: Option<&str>,
     angle: Option<f64>,
     visible: Option<bool>,
-) -> (String, String) {
+) -> Result<EnvironmentHandles, NsiHelperError> {
+    check_finite("angle", angle.unwrap_or(0.0))?;
+
+    environment_oriented(
+        ctx,
+        handle,
+        [0.0, angle.unwrap_or(0.0), 0.0],
+        visible,
+        &[],
+    )
+}
+
+/// Creates a typical environment node with an arbitrary orientation.
+///
+/// This is the same as [`environment()`] but the environment can be
+/// tilted too, e.g. to match the horizon of a back plate.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `euler_xyz` – In degrees; the rotation around the X, Y and Z
+///   axes. These are applied in Z-X-Y order: roll around Z first,
+///   then pitch around X and finally heading around the Y (up) axis.
+///   So `[0, angle, 0]` is the same as [`environment()`]'s `angle`.
+///   Each angle is wrapped into `0..360`.
+///
+/// * `visible` – If the environment is visible to the camera.
+///
+/// Returns the handles of the transform and the created `shader`. Any
+/// `args` are set on the `shader`.
+///
+/// # Errors
+/// If `handle` is invalid or any of `euler_xyz` is not finite.
+pub fn environment_oriented<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    euler_xyz: [f64; 3],
+    visible: Option<bool>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> Result<EnvironmentHandles, NsiHelperError> {
+    let nodes = environment_nodes(ctx, handle, euler_xyz, visible)?;
+
+    let shader = ctx.node(None, nsi::NodeType::Shader, args);
+
+    ctx.append(&nodes.attributes, Some("surfaceshader"), shader.as_str());
+
+    Ok(EnvironmentHandles {
+        transform: nodes.transform,
+        shader,
+    })
+}
+
+/// The handles of the nodes created by [`environment_no_shader()`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnvironmentNodes {
+    /// The transform that orients the environment. This is not
+    /// connected to anything; append it to the scene's root.
+    pub transform: String,
+    /// The `attributes` node of the environment. Connect a shader to
+    /// its `surfaceshader`.
+    pub attributes: String,
+}
+
+/// Creates a typical environment node without a shader.
+///
+/// This is the same as [`environment()`] but the `surfaceshader` of
+/// the environment's `attributes` node is left unconnected. Use this
+/// to hook up your own OSL network without an empty shader node
+/// lingering in the scene.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `angle` – In degrees; specicfies how much to rotate the
+///   environment around the Y (up) axis.
+///
+/// * `visible` – If the environment is visible to the camera.
+///
+/// Returns the handles of the transform and the `attributes` node.
+/// It is up to the caller to connect a shader to the latter. Until
+/// then the environment does not emit any light.
+///
+/// # Errors
+/// If `handle` is invalid or `angle` is not finite.
+pub fn environment_no_shader(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    angle: Option<f64>,
+    visible: Option<bool>,
+) -> Result<EnvironmentNodes, NsiHelperError> {
+    check_finite("angle", angle.unwrap_or(0.0))?;
+
+    environment_nodes(ctx, handle, [0.0, angle.unwrap_or(0.0), 0.0], visible)
+}
+
+// Returns the rotation of an environment, see environment_oriented().
+fn environment_matrix(euler_xyz: [f64; 3]) -> [f64; 16] {
+    let euler_xyz = [
+        normalize_angle(euler_xyz[0]),
+        normalize_angle(euler_xyz[1]),
+        normalize_angle(euler_xyz[2]),
+    ];
+
+    math::multiply(
+        &math::multiply(
+            &math::rotation(euler_xyz[2], [0.0, 0.0, 1.0]),
+            &math::rotation(euler_xyz[0], [1.0, 0.0, 0.0]),
+        ),
+        &math::rotation(euler_xyz[1], [0.0, 1.0, 0.0]),
+    )
+}
+
+// Creates the transform, environment and attributes nodes of an
+// environment.
+fn environment_nodes(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    euler_xyz: [f64; 3],
+    visible: Option<bool>,
+) -> Result<EnvironmentNodes, NsiHelperError> {
+    check_handle(handle)?;
+    for angle in &euler_xyz {
+        check_finite("euler_xyz", *angle)?;
+    }
+
     // Create a rotation transform – this is the handle we return.
-    let rotation = ctx.rotation(
+    let rotation = ctx.node(
         None,
-        angle.unwrap_or(0.0),
-        &[0.0, 1.0, 0.0],
+        nsi::NodeType::Transform,
+        &[nsi::double_matrix!(
+            "transformationmatrix",
+            &environment_matrix(euler_xyz)
+        )],
     );
 
     let environment = generate_or_use_handle(handle, Some("environment"));
@@ -43,24 +258,229 @@ pub fn environment(
         &ctx.node(Some(environment.as_str()), nsi::NodeType::Environment, &[]),
     );
 
-    let shader = ctx.node(None, nsi::NodeType::Shader, &[]);
-
-    ctx.append(
-        &environment,
-        Some("geometryattributes"),
-        ctx.append(
-            &ctx.node(None, nsi::NodeType::Attributes,
-                &[nsi::integer!(
-                    "visibility.camera",
-                    visible.unwrap_or(true) as _
-                )]
-            ),
-            Some("surfaceshader"),
-            shader.as_str()
-        ).0,
+    let attributes = ctx.node(None, nsi::NodeType::Attributes,
+        &[nsi::integer!(
+            "visibility.camera",
+            visible.unwrap_or(true) as _
+        )]
     );
 
-    (rotation, shader)
+    ctx.append(&environment, Some("geometryattributes"), attributes.as_str());
+
+    Ok(EnvironmentNodes {
+        transform: rotation,
+        attributes,
+    })
+}
+
+// Combines a linear `intensity` and an `exposure` in stops.
+#[inline]
+fn intensity(intensity: Option<f32>, exposure: Option<f32>) -> f32 {
+    intensity.unwrap_or(1.0) * 2.0f32.powf(exposure.unwrap_or(0.0))
+}
+
+/// The layout of an environment texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvMapping {
+    /// A latitude-longitude (equirectangular) map.
+    LatLong,
+    /// A photo of a mirror ball (light probe).
+    MirrorBall,
+    /// An angular map, as used by e.g. Debevec's light probes.
+    AngularMap,
+}
+
+impl From<EnvMapping> for Vec<u8> {
+    #[inline]
+    fn from(mapping: EnvMapping) -> Self {
+        match mapping {
+            EnvMapping::LatLong => b"latlong".to_vec(),
+            EnvMapping::MirrorBall => b"mirrorball".to_vec(),
+            EnvMapping::AngularMap => b"angular".to_vec(),
+        }
+    }
+}
+
+/// Projects an environment onto a finite ground.
+///
+/// Objects placed on the ground then appear to stand on the floor
+/// captured in the environment texture instead of floating in front
+/// of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroundProjection {
+    /// The point the environment was captured from, projected onto
+    /// the ground, in scene units.
+    pub center: [f64; 3],
+    /// The radius of the ground disk, in scene units. Beyond this the
+    /// environment is projected onto a dome at infinity as usual.
+    pub radius: f64,
+}
+
+/// Builds a textured environment light.
+///
+/// This is a more readable alternative to [`environment_texture()`]
+/// when only some of its parameters are needed. All parameters but
+/// the `texture` are optional and default to what
+/// [`environment_texture()`] would use for [`None`].
+///
+/// ```no_run
+/// # use nsi_3delight::EnvironmentBuilder;
+/// # fn main() -> Result<(), nsi_3delight::NsiHelperError> {
+/// # let ctx = nsi::Context::new(&[]).unwrap();
+/// let handles = EnvironmentBuilder::new("studio.exr")
+///     .angle(90.0)
+///     .exposure(1.5)
+///     .visible(false)
+///     .build(&ctx)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentBuilder {
+    texture: String,
+    handle: Option<String>,
+    mapping: Option<EnvMapping>,
+    angle: Option<f64>,
+    intensity: Option<f32>,
+    exposure: Option<f32>,
+    tint: Option<[f32; 3]>,
+    ground_projection: Option<GroundProjection>,
+    visible: Option<bool>,
+}
+
+impl EnvironmentBuilder {
+    /// Starts building an environment for `texture`. See
+    /// [`environment_texture()`] for the supported formats.
+    pub fn new(texture: &str) -> Self {
+        Self {
+            texture: texture.to_string(),
+            handle: None,
+            mapping: None,
+            angle: None,
+            intensity: None,
+            exposure: None,
+            tint: None,
+            ground_projection: None,
+            visible: None,
+        }
+    }
+
+    /// The handle of the `environment` node. If not set, a random
+    /// handle is generated.
+    pub fn handle(mut self, handle: &str) -> Self {
+        self.handle = Some(handle.to_string());
+        self
+    }
+
+    /// How the texture is laid out.
+    pub fn mapping(mut self, mapping: EnvMapping) -> Self {
+        self.mapping = Some(mapping);
+        self
+    }
+
+    /// In degrees; how much to rotate the environment around the Y
+    /// (up) axis.
+    pub fn angle(mut self, angle: f64) -> Self {
+        self.angle = Some(angle);
+        self
+    }
+
+    /// A linear multiplier for the intensity.
+    pub fn intensity(mut self, intensity: f32) -> Self {
+        self.intensity = Some(intensity);
+        self
+    }
+
+    /// Scales the intensity in stops.
+    pub fn exposure(mut self, exposure: f32) -> Self {
+        self.exposure = Some(exposure);
+        self
+    }
+
+    /// A color the texture is multiplied with.
+    pub fn tint(mut self, tint: [f32; 3]) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    /// Projects the lower hemisphere onto a ground disk.
+    pub fn ground_projection(mut self, ground_projection: GroundProjection) -> Self {
+        self.ground_projection = Some(ground_projection);
+        self
+    }
+
+    /// If the environment is visible to the camera.
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = Some(visible);
+        self
+    }
+
+    // Returns the attributes of the environment's shader.
+    // `ground_center` is the center of the `ground_projection` in
+    // single precision. ɴsɪ borrows point data so it is passed in to
+    // outlive the attributes.
+    fn shader_attributes<'b, 'a>(&'b self, ground_center: &'b [f32; 3]) -> nsi::ArgVec<'b, 'a> {
+        let mut attributes = vec![
+            nsi::string!("shaderfilename", "${DELIGHT}/osl/environmentLight"),
+            nsi::float!("intensity", intensity(self.intensity, self.exposure)),
+            nsi::string!("image", self.texture.as_str()),
+        ];
+
+        if let Some(mapping) = self.mapping {
+            attributes.push(nsi::string!("mapping", mapping));
+        }
+
+        if let Some(tint) = &self.tint {
+            attributes.push(nsi::color!("tint", tint));
+        }
+
+        if let Some(ground_projection) = &self.ground_projection {
+            attributes.extend(vec![
+                nsi::integer!("groundprojection", 1),
+                nsi::point!("groundprojection_center", ground_center),
+                nsi::double!("groundprojection_radius", ground_projection.radius),
+            ]);
+        }
+
+        attributes
+    }
+
+    /// Creates the environment.
+    ///
+    /// Returns the handles of the transform and the created `shader`.
+    ///
+    /// # Errors
+    /// See [`environment_texture()`].
+    pub fn build(&self, ctx: &nsi::Context) -> Result<EnvironmentHandles, NsiHelperError> {
+        if self.texture.is_empty() {
+            return Err(NsiHelperError::EmptyTexture);
+        }
+        check_finite("intensity", self.intensity.unwrap_or(1.0) as _)?;
+        check_finite("exposure", self.exposure.unwrap_or(0.0) as _)?;
+        if let Some(ground_projection) = self.ground_projection {
+            for value in &ground_projection.center {
+                check_finite("ground_projection.center", *value)?;
+            }
+            check_finite("ground_projection.radius", ground_projection.radius)?;
+        }
+
+        let handles = environment(ctx, self.handle.as_deref(), self.angle, self.visible)?;
+
+        let ground_center = self
+            .ground_projection
+            .map_or([0.0; 3], |ground_projection| {
+                let center = ground_projection.center;
+                [center[0] as f32, center[1] as f32, center[2] as f32]
+            });
+
+        // Environment light attributes.
+        ctx.set_attribute(
+            handles.shader.as_str(),
+            &self.shader_attributes(&ground_center),
+        );
+
+        Ok(handles)
+    }
 }
 
 /// Creates a textured environment light.
@@ -68,58 +488,192 @@ pub fn environment(
 /// If `handle` is [`None`] a random handle is generated.
 ///
 /// # Arguments
-/// * `texture – A latitude-longitude texture map in one of these
-///     formats:
-///     * TIFF
-///     * JPEG
-///     * Radiance
-///     * OpenEXR
-///     * GIF
-///     * IFF
-///     * SGI
-///     * PIC
-///     * Photoshop PSD
-///     * TGA
+/// * `texture – An environment texture map in one of these
+///   formats:
+///   * TIFF
+///   * JPEG
+///   * Radiance
+///   * OpenEXR
+///   * GIF
+///   * IFF
+///   * SGI
+///   * PIC
+///   * Photoshop PSD
+///   * TGA
+///
+/// * `mapping` – How the `texture` is laid out. If [`None`],
+///   [`EnvMapping::LatLong`] is used.
 ///
 /// * `angle` – In degrees; specicfies how much to rotate the
-///     environment around the Y (up) axis.
+///   environment around the Y (up) axis.
+///
+/// * `intensity` – A linear multiplier for the intensity. If
+///   [`None`], `1` is used.
 ///
 /// * `exposure` – Scales the intensity in
-///     [stops or EV values](https://en.wikipedia.org/wiki/Exposure_value).
+///   [stops or EV values](https://en.wikipedia.org/wiki/Exposure_value).
+///   The shader's `intensity` is `intensity * 2^exposure`.
+///
+/// * `tint` – A color the texture is multiplied with. This is
+///   applied on top of the intensity, i.e. it does not replace the
+///   `exposure`. If [`None`], the texture is used as-is.
+///
+/// * `ground_projection` – Projects the lower hemisphere of the
+///   `texture` onto a ground disk. If [`None`], the texture is
+///   projected onto an infinite dome.
 ///
 /// * `visible` – If the environment is visible to the camera.
 ///
-/// Returns `handle` and the handle of the created `shader`.
+/// Returns the handles of the transform and the created `shader`.
 ///
-/// Note that the `shader` node is empty. It is up to the user
-/// to set the resp. attributes on the node or hook up an OSL
-/// network below it.
+/// # Errors
+/// If `handle` is invalid, `texture` is empty or `angle`,
+/// `intensity`, `exposure` or the `ground_projection` are not finite.
+#[allow(clippy::too_many_arguments)]
 pub fn environment_texture<'a, 'b>(
     ctx: &nsi::Context<'a>,
     handle: Option<&str>,
     texture: &str,
+    mapping: Option<EnvMapping>,
     angle: Option<f64>,
+    intensity: Option<f32>,
     exposure: Option<f32>,
+    tint: Option<[f32; 3]>,
+    ground_projection: Option<GroundProjection>,
     visible: Option<bool>,
     args: &nsi::ArgSlice<'b, 'a>,
-) -> (String, String) {
-    let (rotation, shader) = environment(ctx, handle, angle, visible);
+) -> Result<EnvironmentHandles, NsiHelperError> {
+    let handles = EnvironmentBuilder {
+        texture: texture.to_string(),
+        handle: handle.map(|handle| handle.to_string()),
+        mapping,
+        angle,
+        intensity,
+        exposure,
+        tint,
+        ground_projection,
+        visible,
+    }
+    .build(ctx)?;
+
+    if !args.is_empty() {
+        ctx.set_attribute(handles.shader.as_str(), args);
+    }
+
+    Ok(handles)
+}
+
+/// Creates a constant color environment light.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `color` – The color of the environment.
+///
+/// * `intensity` – Scales the `color`.
+///
+/// * `visible` – If the environment is visible to the camera.
+///
+/// Returns the handles of the transform and the created `shader`.
+///
+/// A constant environment is much cheaper to sample than a texture.
+/// This makes it a good choice for turntables and AO-style lighting.
+///
+/// # Errors
+/// If `handle` is invalid or `intensity` is not finite.
+pub fn environment_constant<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    color: [f32; 3],
+    intensity: f32,
+    visible: Option<bool>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> Result<EnvironmentHandles, NsiHelperError> {
+    check_finite("intensity", intensity as _)?;
+
+    let handles = environment(ctx, handle, None, visible)?;
 
     // Environment light attributes.
     ctx.set_attribute(
-        shader.as_str(),
+        handles.shader.as_str(),
         &[
             nsi::string!("shaderfilename", "${DELIGHT}/osl/environmentLight"),
-            nsi::float!("intensity", 2.0f32.powf(exposure.unwrap_or(0.0))),
-            nsi::string!("image", texture),
+            nsi::float!("intensity", intensity),
+            nsi::color!("i_color", &color),
         ],
     );
 
     if !args.is_empty() {
-        ctx.set_attribute(shader.as_str(), args);
+        ctx.set_attribute(handles.shader.as_str(), args);
+    }
+
+    Ok(handles)
+}
+
+// Blends `horizon` to `zenith` with the elevation of the ray.
+const ENVIRONMENT_GRADIENT_SOURCE: &str = r#"
+shader environmentGradient(
+    color horizon = color(0.5),
+    color zenith = color(1),
+    float intensity = 1,
+)
+{
+    vector direction = normalize(transform("object", I));
+    float t = smoothstep(0, 1, direction[1]);
+
+    Ci = intensity * mix(horizon, zenith, t) * emission();
+}
+"#;
+
+/// Creates a gradient environment light.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `horizon` – The color at and below the horizon.
+///
+/// * `zenith` – The color straight up.
+///
+/// * `intensity` – Scales both colors.
+///
+/// * `visible` – If the environment is visible to the camera.
+///
+/// Returns the handles of the transform and the created `shader`.
+///
+/// The colors are blended smoothly with the elevation above the X-Z
+/// plane. The `shader` is compiled from OSL source on the fly so no
+/// shader file needs to be installed alongside 3Delight.
+///
+/// # Errors
+/// If `handle` is invalid or `intensity` is not finite.
+pub fn environment_gradient<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    horizon: [f32; 3],
+    zenith: [f32; 3],
+    intensity: f32,
+    visible: Option<bool>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> Result<EnvironmentHandles, NsiHelperError> {
+    check_finite("intensity", intensity as _)?;
+
+    let handles = environment(ctx, handle, None, visible)?;
+
+    ctx.set_attribute(
+        handles.shader.as_str(),
+        &[
+            nsi::string!("shadersource", ENVIRONMENT_GRADIENT_SOURCE),
+            nsi::color!("horizon", &horizon),
+            nsi::color!("zenith", &zenith),
+            nsi::float!("intensity", intensity),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(handles.shader.as_str(), args);
     }
 
-    (rotation, shader)
+    Ok(handles)
 }
 
 /// **Convenience method; not part of the official ɴsɪ API.**
@@ -131,33 +685,116 @@ pub fn environment_texture<'a, 'b>(
 ///
 /// # Arguments
 /// * `angle` – In degrees; specicfies how much to rotate the
-///     environment around the Y (up) axis.
+///   environment around the Y (up) axis.
+///
+/// * `intensity` – A linear multiplier for the intensity. If
+///   [`None`], `1` is used.
 ///
 /// * `exposure` – Scales the intensity in
-///     [stops or EV values](https://en.wikipedia.org/wiki/Exposure_value).
+///   [stops or EV values](https://en.wikipedia.org/wiki/Exposure_value).
+///   The shader's `intensity` is `intensity * 2^exposure`.
 ///
 /// * `visible` – If the environment is visible to the camera.
 ///
-/// Returns `handle` and the handle of the created `shader`.
+/// Returns the handles of the transform and the created `shader`.
 ///
 /// Note that this instances a `dlSky` shader. Using the returned
 /// `shader` handle you can set more attributes on this node.
+///
+/// # Errors
+/// If `handle` is invalid or `angle`, `intensity` or `exposure` are
+/// not finite.
+#[allow(clippy::too_many_arguments)]
 pub fn environment_sky<'a, 'b>(
     ctx: &nsi::Context<'a>,
     handle: Option<&str>,
     angle: Option<f64>,
+    intensity: Option<f32>,
     exposure: Option<f32>,
     visible: Option<bool>,
     args: &nsi::ArgSlice<'b, 'a>,
-) -> (String, String) {
-    let (rotation, shader) = environment(ctx, handle, angle, visible);
+) -> Result<EnvironmentHandles, NsiHelperError> {
+    check_finite("intensity", intensity.unwrap_or(1.0) as _)?;
+    check_finite("exposure", exposure.unwrap_or(0.0) as _)?;
+
+    let handles = environment(ctx, handle, angle, visible)?;
 
     // Environment light attributes.
     ctx.set_attribute(
-        shader.as_str(),
+        handles.shader.as_str(),
         &[
             nsi::string!("shaderfilename", "${DELIGHT}/osl/dlSky"),
-            nsi::float!("intensity", 2.0f32.powf(exposure.unwrap_or(0.0))),
+            nsi::float!("intensity", self::intensity(intensity, exposure)),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(handles.shader.as_str(), args);
+    }
+
+    Ok(handles)
+}
+
+/// Creates a global atmosphere, e.g. haze or fog.
+///
+/// The atmosphere fills the whole scene with a uniform volume. Light
+/// is scattered by it which makes e.g. god rays visible or creates an
+/// underwater look.
+///
+/// If `handle` is [`None`] a random handle is generated. The handle
+/// names the `environment` node that carries the atmosphere.
+///
+/// # Arguments
+/// * `density` – How thick the atmosphere is. Start with small
+///   values, e.g. `0.01` to `0.1` for a scene in meters.
+///
+///   Render times go up dramatically with the density as every ray
+///   has to be scattered through the volume.
+///
+/// * `color` – The scattering color.
+///
+/// Returns the handles of the transform and the created
+/// `dlAtmosphere` `shader`. Like for the `environment*()` helpers the
+/// transform is not connected to anything; append it to the scene's
+/// root. Pass e.g. `anisotropy` (Henyey-Greenstein `g`, `-1..1`;
+/// positive values scatter forward) via `args`.
+///
+/// # Errors
+/// If `handle` is invalid, `color` is not finite or `density` is not
+/// finite or negative.
+pub fn atmosphere<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    density: f32,
+    color: [f32; 3],
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> Result<EnvironmentHandles, NsiHelperError> {
+    check_handle(handle)?;
+    check_finite("density", density as _)?;
+    for component in &color {
+        check_finite("color", *component as _)?;
+    }
+    if density < 0.0 {
+        return Err(NsiHelperError::OutOfRange("density"));
+    }
+
+    let transform = ctx.node(None, nsi::NodeType::Transform, &[]);
+
+    let environment = generate_or_use_handle(handle, Some("atmosphere"));
+
+    ctx.append(
+        &transform,
+        None,
+        &ctx.node(Some(environment.as_str()), nsi::NodeType::Environment, &[]),
+    );
+
+    let shader = ctx.node(
+        None,
+        nsi::NodeType::Shader,
+        &[
+            nsi::string!("shaderfilename", "${DELIGHT}/osl/dlAtmosphere"),
+            nsi::float!("density", density),
+            nsi::color!("i_color", &color),
         ],
     );
 
@@ -165,5 +802,132 @@ pub fn environment_sky<'a, 'b>(
         ctx.set_attribute(shader.as_str(), args);
     }
 
-    (rotation, shader)
+    ctx.append(
+        &environment,
+        Some("geometryattributes"),
+        ctx.append(
+            &ctx.node(None, nsi::NodeType::Attributes, &[]),
+            Some("volumeshader"),
+            shader.as_str(),
+        )
+        .0,
+    );
+
+    Ok(EnvironmentHandles { transform, shader })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Returns the names of `args`. ɴsɪ does not expose them so they
+    // are read from the `Debug` output.
+    pub(crate) fn arg_names(args: &nsi::ArgSlice) -> Vec<String> {
+        args.iter()
+            .map(|arg| {
+                format!("{:?}", arg)
+                    .split('"')
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn environment_heading_matches_single_axis() {
+        for &angle in &[0.0, 30.0, 90.0, 270.0, -90.0, 450.0] {
+            let oriented = environment_matrix([0.0, angle, 0.0]);
+            let single_axis = math::rotation(normalize_angle(angle), [0.0, 1.0, 0.0]);
+
+            for (oriented, single_axis) in oriented.iter().zip(single_axis.iter()) {
+                assert!((oriented - single_axis).abs() < 1.0e-12, "{}", angle);
+            }
+        }
+    }
+
+    #[test]
+    fn environment_angle_is_in_degrees() {
+        // The angle around Y that +X is turned by.
+        let x =
+            math::tests::transform_point(&environment_matrix([0.0, 90.0, 0.0]), [1.0, 0.0, 0.0]);
+        let radians = (-x[2]).atan2(x[0]);
+
+        assert!(
+            (std::f64::consts::TAU / 4.0 - radians).abs() < 1.0e-12,
+            "{}",
+            radians
+        );
+    }
+
+    // Returns the names of the attributes of the shader `builder`
+    // creates.
+    fn shader_attribute_names(builder: EnvironmentBuilder) -> Vec<String> {
+        arg_names(&builder.shader_attributes(&[0.0; 3]))
+    }
+
+    #[test]
+    fn tint_only_when_given() {
+        let tint = "tint".to_string();
+        let builder = EnvironmentBuilder::new("studio.exr");
+
+        assert!(!shader_attribute_names(builder.clone()).contains(&tint));
+        assert!(shader_attribute_names(builder.tint([1.0, 0.9, 0.8])).contains(&tint));
+    }
+
+    #[test]
+    fn env_mapping_names() {
+        let builder = EnvironmentBuilder::new("probe.exr");
+
+        assert!(!shader_attribute_names(builder.clone()).contains(&"mapping".to_string()));
+
+        for &(mapping, name) in &[
+            (EnvMapping::LatLong, "latlong"),
+            (EnvMapping::MirrorBall, "mirrorball"),
+            (EnvMapping::AngularMap, "angular"),
+        ] {
+            assert_eq!(name.as_bytes().to_vec(), Vec::<u8>::from(mapping));
+
+            // The value follows the name in the `Debug` output.
+            let builder = builder.clone().mapping(mapping);
+            let attributes = builder.shader_attributes(&[0.0; 3]);
+            let attribute = format!("{:?}", attributes.last().unwrap());
+            let strings = attribute.split('"').skip(1).step_by(2);
+            assert_eq!(vec!["mapping", name], strings.take(2).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn ground_projection_only_when_given() {
+        let builder = EnvironmentBuilder::new("backplate.exr");
+        let names = shader_attribute_names(builder.clone());
+
+        assert!(!names
+            .iter()
+            .any(|name| name.starts_with("groundprojection")));
+
+        let names = shader_attribute_names(builder.ground_projection(GroundProjection {
+            center: [0.0, 1.5, 0.0],
+            radius: 20.0,
+        }));
+
+        assert_eq!(
+            vec![
+                "groundprojection",
+                "groundprojection_center",
+                "groundprojection_radius"
+            ],
+            names
+                .iter()
+                .filter(|name| name.starts_with("groundprojection"))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn angles_are_wrapped() {
+        assert_eq!(90.0, normalize_angle(450.0));
+        assert_eq!(270.0, normalize_angle(-90.0));
+        assert_eq!(0.0, normalize_angle(-1.0e-20));
+    }
 }
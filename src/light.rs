@@ -0,0 +1,1027 @@
+//! Shortcuts for instancing lights.
+//!
+//! In ɴsɪ a light is just geometry with an emissive surface shader.
+use crate::{
+    material::{
+        assign_material, assign_material_with_attributes, emissive_material, source_shader,
+    },
+    math,
+};
+use nsi::toolbelt::generate_or_use_handle;
+
+// Creates a light shader from one of the OSL shaders 3Delight ships.
+fn light_shader<'a>(
+    ctx: &nsi::Context<'a>,
+    shader_file_name: &str,
+    intensity: f32,
+    color: [f32; 3],
+    args: &nsi::ArgSlice<'_, 'a>,
+) -> String {
+    let shader = ctx.node(
+        None,
+        nsi::NodeType::Shader,
+        &[
+            nsi::string!("shaderfilename", shader_file_name),
+            nsi::float!("intensity", intensity),
+            nsi::color!("i_color", &color),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(shader.as_str(), args);
+    }
+
+    shader
+}
+
+/// The handles of a light, as returned by e.g. [`area_light()`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LightHandles {
+    /// The transform the light is appended to.
+    pub transform: String,
+    /// The light's geometry, e.g. to pass to
+    /// [`set_visibility()`](crate::set_visibility()) or
+    /// [`link_light()`].
+    pub geometry: String,
+    /// The light's shader, e.g. to pass to [`set_light_decay()`].
+    pub shader: String,
+}
+
+impl From<LightHandles> for (String, String) {
+    #[inline]
+    fn from(handles: LightHandles) -> Self {
+        (handles.transform, handles.shader)
+    }
+}
+
+/// Creates a distant (directional) light, e.g. a sun.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `direction` – The direction the light travels in. Does *not*
+///   need to be normalized.
+///
+/// * `intensity` – Scales the light's `color`.
+///
+/// * `color` – The color of the light.
+///
+/// * `angle` – In degrees; the angular diameter of the light source.
+///   Larger values give softer shadows. If [`None`], `0` is used
+///   which gives perfectly hard shadows. For reference: the sun's
+///   angular diameter is about `0.53`.
+///
+/// Returns the [`LightHandles`] of the light. Its `transform` orients
+/// the light.
+///
+/// Note that the transform is not connected to anything. It is up to
+/// the user to append it to the scene's root.
+pub fn distant_light<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    direction: [f64; 3],
+    intensity: f32,
+    color: [f32; 3],
+    angle: Option<f64>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    // The light comes *from* the environment's +Z axis.
+    let transform = ctx.node(
+        None,
+        nsi::NodeType::Transform,
+        &[nsi::double_matrix!(
+            "transformationmatrix",
+            &math::align_z([-direction[0], -direction[1], -direction[2]])
+        )],
+    );
+
+    let light = generate_or_use_handle(handle, Some("distant_light"));
+
+    ctx.append(
+        &transform,
+        None,
+        &ctx.node(
+            Some(light.as_str()),
+            nsi::NodeType::Environment,
+            &[nsi::double!("angle", angle.unwrap_or(0.0))],
+        ),
+    );
+
+    let shader = light_shader(
+        ctx,
+        "${DELIGHT}/osl/directionalLight",
+        intensity,
+        color,
+        args,
+    );
+
+    assign_material(ctx, light.as_str(), shader.as_str());
+
+    LightHandles {
+        transform,
+        geometry: light,
+        shader,
+    }
+}
+
+/// Creates a spot light.
+///
+/// The light is a small disk, one centimeter in diameter if the
+/// scene is in meters, at the origin of `transform` that shines along
+/// its -Z axis.
+///
+/// If `handle` is [`None`] a random handle is generated. The handle
+/// names the light's geometry. Set e.g. its `width` to change the
+/// size of the disk.
+///
+/// # Arguments
+/// * `cone_angle` – In degrees; the *full* opening angle of the cone.
+///
+/// * `penumbra` – In degrees; softens the edge of the cone by fading
+///   the light out over this angle.
+///
+/// * `intensity` – Scales the light's `color`.
+///
+/// * `color` – The color of the light.
+///
+/// * `transform` – The transform node the light is appended to.
+///
+/// Returns the [`LightHandles`] of the light.
+#[allow(clippy::too_many_arguments)]
+pub fn spot_light<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    cone_angle: f64,
+    penumbra: f64,
+    intensity: f32,
+    color: [f32; 3],
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    let light = generate_or_use_handle(handle, Some("spot_light"));
+
+    // A particle with a normal is rendered as a disk.
+    ctx.node(
+        Some(light.as_str()),
+        nsi::NodeType::Particles,
+        &[
+            nsi::points!("P", &[0.0, 0.0, 0.0]),
+            nsi::normals!("N", &[0.0, 0.0, -1.0]),
+            nsi::floats!("width", &[0.01]),
+        ],
+    );
+    ctx.append(transform, None, light.as_str());
+
+    let shader = light_shader(ctx, "${DELIGHT}/osl/spotLight", intensity, color, &[]);
+
+    ctx.set_attribute(
+        shader.as_str(),
+        &[
+            nsi::float!("coneAngle", cone_angle as f32),
+            nsi::float!("penumbraAngle", penumbra as f32),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(shader.as_str(), args);
+    }
+
+    assign_material(ctx, light.as_str(), shader.as_str());
+
+    LightHandles {
+        transform: transform.to_string(),
+        geometry: light,
+        shader,
+    }
+}
+
+/// Creates an omnidirectional point light.
+///
+/// If `handle` is [`None`] a random handle is generated. The handle
+/// names the light's geometry.
+///
+/// # Arguments
+/// * `intensity` – Scales the light's `color`.
+///
+/// * `color` – The color of the light.
+///
+/// * `radius` – If given, the light is a sphere of this radius which
+///   casts soft shadows. If [`None`], the light is an infinitely small
+///   point which casts hard shadows.
+///
+/// * `transform` – The transform node the light is appended to.
+///
+/// Returns the [`LightHandles`] of the light.
+pub fn point_light<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    intensity: f32,
+    color: [f32; 3],
+    radius: Option<f64>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    let light = generate_or_use_handle(handle, Some("point_light"));
+
+    // A particle without a normal is rendered as a sphere.
+    ctx.node(
+        Some(light.as_str()),
+        nsi::NodeType::Particles,
+        &[
+            nsi::points!("P", &[0.0, 0.0, 0.0]),
+            nsi::floats!("width", &[2.0 * radius.unwrap_or(0.0) as f32]),
+        ],
+    );
+    ctx.append(transform, None, light.as_str());
+
+    let shader = light_shader(ctx, "${DELIGHT}/osl/pointLight", intensity, color, args);
+
+    assign_material(ctx, light.as_str(), shader.as_str());
+
+    LightHandles {
+        transform: transform.to_string(),
+        geometry: light,
+        shader,
+    }
+}
+
+/// Creates a rectangular area light, e.g. a softbox.
+///
+/// The light lies in the X-Y plane of `transform`, centered at its
+/// origin, and shines along its -Z axis.
+///
+/// If `handle` is [`None`] a random handle is generated. The handle
+/// names the light's geometry.
+///
+/// # Arguments
+/// * `width`, `height` – The size of the light along X and Y.
+///
+/// * `intensity` – Scales the light's `color`.
+///
+/// * `color` – The color of the light.
+///
+/// * `transform` – The transform node the light is appended to.
+///
+/// The light is not visible to the camera so the panel does not show
+/// up in renders. This is set as `visibility.camera` on the
+/// `attributes` node of the light's geometry. Use
+/// [`set_visibility()`](crate::set_visibility()) on the geometry to
+/// show it. `args` are set on the `shader`.
+///
+/// To divide the intensity by the area of the light use
+/// [`set_light_normalize()`].
+///
+/// Returns the [`LightHandles`] of the light.
+#[allow(clippy::too_many_arguments)]
+pub fn area_light<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    width: f64,
+    height: f64,
+    intensity: f32,
+    color: [f32; 3],
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    let light = generate_or_use_handle(handle, Some("area_light"));
+
+    let (x, y) = (0.5 * width as f32, 0.5 * height as f32);
+
+    // Wound so the front face points along -Z.
+    ctx.node(
+        Some(light.as_str()),
+        nsi::NodeType::Mesh,
+        &[
+            nsi::points!("P", &[-x, -y, 0.0, -x, y, 0.0, x, y, 0.0, x, -y, 0.0]),
+            nsi::integers!("nvertices", &[4]),
+        ],
+    );
+    ctx.append(transform, None, light.as_str());
+
+    let shader = light_shader(ctx, "${DELIGHT}/osl/areaLight", intensity, color, args);
+
+    assign_material_with_attributes(
+        ctx,
+        light.as_str(),
+        shader.as_str(),
+        &[nsi::integer!("visibility.camera", 0)],
+    );
+
+    LightHandles {
+        transform: transform.to_string(),
+        geometry: light,
+        shader,
+    }
+}
+
+/// The number of segments around a [`cylinder_light()`] if none is
+/// given.
+pub const CYLINDER_LIGHT_SEGMENTS: u32 = 32;
+
+/// Creates a round area light.
+///
+/// The light lies in the X-Y plane of `transform`, centered at its
+/// origin, and shines along its -Z axis.
+///
+/// The disk is exact (it is not tessellated).
+///
+/// If `handle` is [`None`] a random handle is generated. The handle
+/// names the light's geometry.
+///
+/// # Arguments
+/// * `radius` – The radius of the disk.
+///
+/// * `intensity` – Scales the light's `color`.
+///
+/// * `color` – The color of the light.
+///
+/// * `transform` – The transform node the light is appended to.
+///
+/// Like [`area_light()`], the light is not visible to the camera and
+/// `args` are set on the `shader`.
+///
+/// Returns the [`LightHandles`] of the light.
+pub fn disk_light<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    radius: f64,
+    intensity: f32,
+    color: [f32; 3],
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    let light = generate_or_use_handle(handle, Some("disk_light"));
+
+    // A particle with a normal is rendered as a disk.
+    ctx.node(
+        Some(light.as_str()),
+        nsi::NodeType::Particles,
+        &[
+            nsi::points!("P", &[0.0, 0.0, 0.0]),
+            nsi::normals!("N", &[0.0, 0.0, -1.0]),
+            nsi::floats!("width", &[2.0 * radius as f32]),
+        ],
+    );
+    ctx.append(transform, None, light.as_str());
+
+    let shader = light_shader(ctx, "${DELIGHT}/osl/areaLight", intensity, color, args);
+
+    assign_material_with_attributes(
+        ctx,
+        light.as_str(),
+        shader.as_str(),
+        &[nsi::integer!("visibility.camera", 0)],
+    );
+
+    LightHandles {
+        transform: transform.to_string(),
+        geometry: light,
+        shader,
+    }
+}
+
+/// Creates a tube-shaped area light, e.g. a fluorescent tube.
+///
+/// The light is an open cylinder along the X axis of `transform`,
+/// centered at its origin, that shines outwards.
+///
+/// If `handle` is [`None`] a random handle is generated. The handle
+/// names the light's geometry.
+///
+/// # Arguments
+/// * `radius` – The radius of the tube.
+///
+/// * `length` – The length of the tube.
+///
+/// * `intensity` – Scales the light's `color`.
+///
+/// * `color` – The color of the light.
+///
+/// * `segments` – The number of facets around the tube. If [`None`],
+///   [`CYLINDER_LIGHT_SEGMENTS`] is used. Increase this for large
+///   lights that are seen in reflections.
+///
+/// * `transform` – The transform node the light is appended to.
+///
+/// Like [`area_light()`], the light is not visible to the camera and
+/// `args` are set on the `shader`.
+///
+/// Unlike [`area_light()`] and [`disk_light()`] this takes the extra
+/// `segments` argument. The tube is tessellated before anything is
+/// sent to the renderer and `args` are only set on the light's
+/// `shader` so the number of segments cannot be passed through them.
+///
+/// Returns the [`LightHandles`] of the light.
+#[allow(clippy::too_many_arguments)]
+pub fn cylinder_light<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    radius: f64,
+    length: f64,
+    intensity: f32,
+    color: [f32; 3],
+    segments: Option<u32>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    let light = generate_or_use_handle(handle, Some("cylinder_light"));
+
+    let segments = segments.unwrap_or(CYLINDER_LIGHT_SEGMENTS).max(3) as i32;
+    let x = 0.5 * length as f32;
+
+    // Two rings of points, one at each end of the tube.
+    let positions = [-x, x]
+        .iter()
+        .flat_map(|&x| {
+            (0..segments).flat_map(move |i| {
+                let angle = core::f64::consts::TAU * i as f64 / segments as f64;
+                vec![
+                    x,
+                    (radius * angle.cos()) as f32,
+                    (radius * angle.sin()) as f32,
+                ]
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Wound so the front faces point outwards.
+    let indices = (0..segments)
+        .flat_map(|i| {
+            let j = (i + 1) % segments;
+            vec![i, j, segments + j, segments + i]
+        })
+        .collect::<Vec<_>>();
+
+    ctx.node(
+        Some(light.as_str()),
+        nsi::NodeType::Mesh,
+        &[
+            nsi::points!("P", &positions),
+            nsi::integers!("P.indices", &indices),
+            nsi::integers!("nvertices", &vec![4; segments as _]),
+        ],
+    );
+    ctx.append(transform, None, light.as_str());
+
+    let shader = light_shader(ctx, "${DELIGHT}/osl/areaLight", intensity, color, args);
+
+    assign_material_with_attributes(
+        ctx,
+        light.as_str(),
+        shader.as_str(),
+        &[nsi::integer!("visibility.camera", 0)],
+    );
+
+    LightHandles {
+        transform: transform.to_string(),
+        geometry: light,
+        shader,
+    }
+}
+
+/// Turns existing geometry into a light.
+///
+/// Unlike the other light helpers this does not create any geometry
+/// or transforms. It only assigns an emissive surface shader to
+/// `geometry`.
+///
+/// # Arguments
+/// * `geometry` – The handle of the geometry to make emissive.
+///
+/// * `intensity` – Scales the light's `color`.
+///
+/// * `color` – The color of the light.
+///
+/// Returns the handle of the created `shader`.
+///
+/// Note that the geometry should not have a surface shader
+/// assigned already. The two would compete for the same slot and one
+/// of them would be overridden.
+pub fn mesh_light<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    geometry: &str,
+    intensity: f32,
+    color: [f32; 3],
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let shader = emissive_material(ctx, None, color, intensity, args);
+
+    assign_material(ctx, geometry, shader.as_str());
+
+    shader
+}
+
+/// Converts a color temperature to a linear RGB color.
+///
+/// This approximates the color of a black body radiator (the
+/// Planckian locus) at `temperature` Kelvin. The result is normalized
+/// so its largest component is `1`; use a light's `intensity` to set
+/// its brightness.
+///
+/// The temperature is clamped to `1000..=40000`. Typical values are
+/// `1900` for candle light, `3200` for tungsten, `5500` for daylight
+/// and `6500` for an overcast sky, which is almost white.
+pub fn kelvin_to_rgb(temperature: f64) -> [f32; 3] {
+    // Tanner Helland's fit of the CIE 1964 10° color matching
+    // functions. This yields sRGB-encoded values in 0..255.
+    let t = temperature.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+
+    let green = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+
+    // Decode to linear.
+    let linear = |c: f64| {
+        let c = c.clamp(0.0, 255.0) / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let rgb = [linear(red), linear(green), linear(blue)];
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+
+    [
+        (rgb[0] / max) as f32,
+        (rgb[1] / max) as f32,
+        (rgb[2] / max) as f32,
+    ]
+}
+
+/// Creates a point light whose color is given as a temperature.
+///
+/// This is the same as [`point_light()`] with the color of the light
+/// computed by [`kelvin_to_rgb()`].
+///
+/// # Arguments
+/// * `temperature` – The color temperature in Kelvin.
+pub fn point_light_kelvin<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    intensity: f32,
+    temperature: f64,
+    radius: Option<f64>,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    point_light(
+        ctx,
+        handle,
+        intensity,
+        kelvin_to_rgb(temperature),
+        radius,
+        transform,
+        args,
+    )
+}
+
+/// Creates a rectangular area light whose color is given as a
+/// temperature.
+///
+/// This is the same as [`area_light()`] with the color of the light
+/// computed by [`kelvin_to_rgb()`].
+///
+/// # Arguments
+/// * `temperature` – The color temperature in Kelvin. E.g. `3200` for
+///   a tungsten key light:
+///   ```no_run
+///   # let ctx = nsi::Context::new(&[]).unwrap();
+///   # let transform = ".root";
+///   nsi_3delight::area_light_kelvin(&ctx, None, 1.0, 1.0, 2.0, 3200.0, transform, &[]);
+///   ```
+#[allow(clippy::too_many_arguments)]
+pub fn area_light_kelvin<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    width: f64,
+    height: f64,
+    intensity: f32,
+    temperature: f64,
+    transform: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> LightHandles {
+    area_light(
+        ctx,
+        handle,
+        width,
+        height,
+        intensity,
+        kelvin_to_rgb(temperature),
+        transform,
+        args,
+    )
+}
+
+/// How [`link_light()`] treats the geometry it is given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkMode {
+    /// The light *only* illuminates the given geometry.
+    Include,
+    /// The light illuminates everything *but* the given geometry.
+    Exclude,
+}
+
+/// Restricts which geometry a light illuminates.
+///
+/// # Arguments
+/// * `light` – The handle of the light's geometry (the `handle` given
+///   to e.g. [`spot_light()`]) or of a transform or set containing
+///   lights.
+///
+/// * `geometry` – The handles of the geometry to include or exclude.
+///   These can also be transforms; geometry below them is linked too.
+///   If this is empty, nothing is done.
+///
+/// * `mode` – Whether `geometry` is the only geometry lit or the only
+///   geometry not lit.
+///
+/// In ɴsɪ, light linking is done through the `visibility` attribute
+/// of `attributes` nodes. `light` is connected to the `visibility` of
+/// an `attributes` node with a `value` of `0` that is in turn
+/// connected to the `geometryattributes` of `geometry`. The light
+/// then does not see – and thus does not illuminate – the geometry.
+///
+/// For [`LinkMode::Include`] this is done for the scene root instead
+/// and `geometry` gets a second `attributes` node with a `value` of
+/// `1` and a higher `priority` that overrides the former.
+pub fn link_light(ctx: &nsi::Context, light: &str, geometry: &[&str], mode: LinkMode) {
+    if geometry.is_empty() {
+        return;
+    }
+
+    // Connects `light` to the visibility of a new attributes node and
+    // returns its handle.
+    let visibility = |value: i32, priority: i32| {
+        let attributes = ctx.node(None, nsi::NodeType::Attributes, &[]);
+        ctx.connect(
+            light,
+            "",
+            attributes.as_str(),
+            "visibility",
+            &[
+                nsi::integer!("value", value),
+                nsi::integer!("priority", priority),
+            ],
+        );
+        attributes
+    };
+
+    let attributes = match mode {
+        LinkMode::Include => {
+            ctx.append(
+                ".root",
+                Some("geometryattributes"),
+                visibility(0, 0).as_str(),
+            );
+            visibility(1, 1)
+        }
+        LinkMode::Exclude => visibility(0, 0),
+    };
+
+    for geometry in geometry {
+        ctx.append(geometry, Some("geometryattributes"), attributes.as_str());
+    }
+}
+
+/// Sets a photometric (IES) profile on a light.
+///
+/// # Arguments
+/// * `light_shader` – The handle of a light's `shader`, as returned
+///   by e.g. [`spot_light()`] or [`point_light()`].
+///
+/// * `ies_path` – The path of the `.ies` file. Environment variables
+///   in the form `${NAME}`, e.g. `${DELIGHT}` or `${HOME}`, are
+///   expanded by the renderer.
+///
+/// * `normalize` – If set, the profile only shapes the light and its
+///   brightness is still controlled solely by the `intensity` of the
+///   light. Otherwise the absolute candela values of the profile are
+///   used too.
+///
+/// The profile is oriented by the light's transform. The profile's
+/// nadir, i.e. the direction a downlight shines, is the -Z axis the
+/// light helpers in this crate use.
+pub fn set_ies_profile(ctx: &nsi::Context, light_shader: &str, ies_path: &str, normalize: bool) {
+    ctx.set_attribute(
+        light_shader,
+        &[
+            nsi::string!("iesprofile", ies_path),
+            nsi::integer!("iesprofile_normalize", normalize as _),
+        ],
+    );
+}
+
+// Looks up a texture by the direction light leaves a spot light in,
+// projected through the cone like a slide through a projector
+// lens. `I` points from the lit point to the light.
+const GOBO_SOURCE: &str = r#"
+shader goboProjection(
+    string textureFile = "",
+    float coneAngle = 40,
+    color i_color = 1,
+    output color outColor = 0,
+)
+{
+    vector d = transform("object", -I);
+
+    if (d[2] < 0) {
+        float w = -d[2] * tan(radians(0.5 * coneAngle));
+
+        outColor = i_color * texture(
+            textureFile,
+            0.5 + 0.5 * d[0] / w,
+            0.5 - 0.5 * d[1] / w,
+            "wrap",
+            "black"
+        );
+    }
+}
+"#;
+
+/// Projects an image through a light, e.g. clouds or a window
+/// pattern.
+///
+/// # Arguments
+/// * `light_shader` – The handle of a light's `shader`, as returned by
+///   [`spot_light()`].
+///
+/// * `texture` – The image to project, in any of the formats
+///   [`environment_texture()`](crate::environment_texture()) supports.
+///
+/// * `cone_angle` – In degrees; the `cone_angle` the light was created
+///   with. The image is fit to the cone such that its width and height
+///   span this angle.
+///
+/// * `color` – The `color` the light was created with. The image is
+///   multiplied with it.
+///
+/// Returns the handle of the created projection `shader`. Any `args`
+/// are set on it. The `shader` is compiled from OSL source on the
+/// fly.
+///
+/// The texture is looked up by the direction light leaves the light
+/// in, in the space of the light's transform. Like a slide in a
+/// projector, the image's center is on the cone's axis; its top points
+/// along the light's +Y axis. Outside the image the light is black.
+/// The result is connected to the light's color, replacing it, so it
+/// is multiplied with the light's `intensity`. Pass the light's color,
+/// or e.g. that of [`kelvin_to_rgb()`], as `color` to keep its tint.
+pub fn set_light_gobo<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    light_shader: &str,
+    texture: &str,
+    cone_angle: f64,
+    color: [f32; 3],
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let gobo = source_shader(
+        ctx,
+        None,
+        "gobo",
+        GOBO_SOURCE,
+        &[
+            nsi::string!("textureFile", texture),
+            nsi::float!("coneAngle", cone_angle as f32),
+            nsi::color!("i_color", &color),
+        ],
+        args,
+    );
+
+    ctx.connect(gobo.as_str(), "outColor", light_shader, "i_color", &[]);
+
+    gobo
+}
+
+/// Restricts which kinds of shading a light contributes to.
+///
+/// E.g. a rim light that only shows up in highlights has `specular`
+/// set but not `diffuse`.
+///
+/// # Arguments
+/// * `light_shader` – The handle of a light's `shader`, as returned
+///   by e.g. [`area_light()`].
+///
+/// * `diffuse`, `specular`, `hair`, `volume` – Whether the light
+///   contributes to diffuse and specular reflection and to hair and
+///   volume shading.
+///
+/// Lights contribute to everything by default. Only the categories
+/// that are turned off are set, as `<category>_contribution` = `0` on
+/// the shader. Calling this again thus does not turn a category back
+/// on; use [`delete_attribute()`](nsi::Context::delete_attribute()) on
+/// the shader for that.
+///
+/// This is a look-dev tool. Real lights have no analogue to it and
+/// scenes using it are not physically plausible any more.
+pub fn set_light_contribution(
+    ctx: &nsi::Context,
+    light_shader: &str,
+    diffuse: bool,
+    specular: bool,
+    hair: bool,
+    volume: bool,
+) {
+    let attributes = contribution_attributes(diffuse, specular, hair, volume);
+
+    if !attributes.is_empty() {
+        ctx.set_attribute(light_shader, &attributes);
+    }
+}
+
+// Returns the `*_contribution` attributes of the categories a light
+// does not contribute to.
+fn contribution_attributes<'b, 'a>(
+    diffuse: bool,
+    specular: bool,
+    hair: bool,
+    volume: bool,
+) -> nsi::ArgVec<'b, 'a> {
+    [
+        ("diffuse_contribution", diffuse),
+        ("specular_contribution", specular),
+        ("hair_contribution", hair),
+        ("volume_contribution", volume),
+    ]
+    .iter()
+    .copied()
+    .filter(|&(_, contributes)| !contributes)
+    .map(|(name, _)| nsi::float!(name, 0.0))
+    .collect()
+}
+
+/// Shapes the cone of a spot light into a rectangle, like the flaps
+/// on a theatre or film fixture.
+///
+/// # Arguments
+/// * `light_shader` – The handle of a light's `shader`, as returned by
+///   [`spot_light()`].
+///
+/// * `top`, `bottom`, `left`, `right` – In degrees; how far each flap
+///   closes in from the edge of the cone towards its axis. Each is
+///   clamped to `0..=90`. At `0` a flap is fully open.
+///
+/// Barn doors crop the cone *after* the penumbra is applied. I.e. the
+/// penumbra still softens the round edge of the cone while the edges
+/// the doors cut are hard. Set all four to `0` to remove the cropping.
+///
+/// Top and bottom are along the light's Y axis, left and right along
+/// its X axis.
+pub fn set_barn_doors(
+    ctx: &nsi::Context,
+    light_shader: &str,
+    top: f64,
+    bottom: f64,
+    left: f64,
+    right: f64,
+) {
+    ctx.set_attribute(
+        light_shader,
+        &[
+            nsi::float!("barnDoorTop", top.clamp(0.0, 90.0) as f32),
+            nsi::float!("barnDoorBottom", bottom.clamp(0.0, 90.0) as f32),
+            nsi::float!("barnDoorLeft", left.clamp(0.0, 90.0) as f32),
+            nsi::float!("barnDoorRight", right.clamp(0.0, 90.0) as f32),
+        ],
+    );
+}
+
+/// How the light of a local light falls off with distance. See
+/// [`set_light_decay()`].
+///
+/// The default is [`DecayMode::Quadratic`].
+///
+/// The mode maps to the exponent of the distance the intensity is
+/// divided by:
+/// ```
+/// # use nsi_3delight::DecayMode;
+/// assert_eq!(0, i32::from(DecayMode::None));
+/// assert_eq!(1, i32::from(DecayMode::Linear));
+/// assert_eq!(2, i32::from(DecayMode::Quadratic));
+/// assert_eq!(3, i32::from(DecayMode::Cubic));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DecayMode {
+    /// The intensity does not depend on the distance from the light.
+    None,
+    /// The intensity falls off with the distance.
+    Linear,
+    /// The intensity falls off with the square of the distance. This is
+    /// what real lights do.
+    #[default]
+    Quadratic,
+    /// The intensity falls off with the cube of the distance.
+    Cubic,
+}
+
+impl From<DecayMode> for i32 {
+    #[inline]
+    fn from(mode: DecayMode) -> Self {
+        match mode {
+            DecayMode::None => 0,
+            DecayMode::Linear => 1,
+            DecayMode::Quadratic => 2,
+            DecayMode::Cubic => 3,
+        }
+    }
+}
+
+/// Sets how a light's intensity falls off with distance.
+///
+/// # Arguments
+/// * `light_shader` – The handle of a light's `shader`, as returned
+///   by e.g. [`point_light()`] or [`spot_light()`].
+///
+/// * `mode` – The falloff. [`DecayMode::Quadratic`] is physically
+///   correct. [`DecayMode::None`] makes the intensity independent of
+///   the distance, which is often what is wanted for a fill light.
+///
+/// The exponent is set as the `decayRate` of the shader. This only
+/// affects local lights. Distant lights and environments are
+/// infinitely far away and do not fall off.
+pub fn set_light_decay(ctx: &nsi::Context, light_shader: &str, mode: DecayMode) {
+    ctx.set_attribute(light_shader, &[nsi::integer!("decayRate", i32::from(mode))]);
+}
+
+/// Sets whether an area light's intensity is divided by its area.
+///
+/// # Arguments
+/// * `light_shader` – The handle of the `shader` of an
+///   [`area_light()`], [`disk_light()`] or [`cylinder_light()`].
+///
+/// * `normalize` – If set, the light emits the same total power
+///   whatever its size. Otherwise each unit of its surface emits
+///   `intensity` and a larger light is brighter.
+///
+/// Lights are not normalized by default. This is how real panels
+/// behave. Normalizing makes it possible to resize a light to soften
+/// its shadows without re-balancing the exposure. But the brightness
+/// seen on the light itself, e.g. in reflections, then changes with
+/// its size.
+///
+/// This sets the `normalize` attribute of the shader.
+pub fn set_light_normalize(ctx: &nsi::Context, light_shader: &str, normalize: bool) {
+    ctx.set_attribute(light_shader, &[nsi::integer!("normalize", normalize as _)]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kelvin_6500_is_white() {
+        for &component in &kelvin_to_rgb(6500.0) {
+            assert!((component - 1.0).abs() < 0.05, "{}", component);
+        }
+    }
+
+    #[test]
+    fn kelvin_is_clamped() {
+        assert_eq!(kelvin_to_rgb(1000.0), kelvin_to_rgb(500.0));
+        assert_eq!(kelvin_to_rgb(40000.0), kelvin_to_rgb(100000.0));
+    }
+
+    #[test]
+    fn kelvin_is_normalized() {
+        for &temperature in &[1000.0, 1900.0, 3200.0, 5500.0, 6500.0, 10000.0, 40000.0] {
+            let [red, green, blue] = kelvin_to_rgb(temperature);
+            assert!((red.max(green).max(blue) - 1.0).abs() < 1.0e-6);
+        }
+    }
+
+    #[test]
+    fn only_disabled_contributions_are_set() {
+        use crate::tests::arg_names;
+
+        assert!(contribution_attributes(true, true, true, true).is_empty());
+        assert_eq!(
+            vec!["diffuse_contribution", "volume_contribution"],
+            arg_names(&contribution_attributes(false, true, true, false))
+        );
+        assert_eq!(
+            vec![
+                "diffuse_contribution",
+                "specular_contribution",
+                "hair_contribution",
+                "volume_contribution"
+            ],
+            arg_names(&contribution_attributes(false, false, false, false))
+        );
+    }
+}
@@ -0,0 +1,1037 @@
+//! Shortcuts for instancing materials.
+use crate::geometry::geometry_attributes;
+use nsi::toolbelt::generate_or_use_handle;
+
+/// Assigns a material to geometry.
+///
+/// # Arguments
+/// * `geometry` – The handle of the geometry, e.g. as returned by
+///   [`mesh()`](crate::mesh()). This can also be a transform; the
+///   material is then inherited by all geometry below it.
+///
+/// * `shader` – The handle of the material's surface shader, e.g. as
+///   returned by [`principled_material()`].
+///
+/// The shader is connected to the `surfaceshader` of the `attributes`
+/// node of `geometry`, with the handle `<geometry>_attributes`. It is
+/// created and connected to the `geometryattributes` of `geometry` if
+/// it does not exist yet. Assigning another material replaces the
+/// previous one.
+pub fn assign_material(ctx: &nsi::Context, geometry: &str, shader: &str) {
+    assign_material_with_attributes(ctx, geometry, shader, &[]);
+}
+
+// Assigns `shader` as the surface shader of `geometry` and sets
+// `attributes` on the attributes node of `geometry`.
+pub(crate) fn assign_material_with_attributes<'a>(
+    ctx: &nsi::Context<'a>,
+    geometry: &str,
+    shader: &str,
+    attributes: &nsi::ArgSlice<'_, 'a>,
+) {
+    let geometry_attributes = geometry_attributes(ctx, geometry);
+
+    if !attributes.is_empty() {
+        ctx.set_attribute(geometry_attributes.as_str(), attributes);
+    }
+
+    ctx.disconnect(".all", "", geometry_attributes.as_str(), "surfaceshader");
+    ctx.append(geometry_attributes.as_str(), Some("surfaceshader"), shader);
+}
+
+// Creates a shader node from one of the OSL shaders 3Delight ships
+// and sets `attributes` followed by `args` on it.
+fn material<'a>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    shader_file_name: &str,
+    attributes: &nsi::ArgSlice<'_, 'a>,
+    args: &nsi::ArgSlice<'_, 'a>,
+) -> String {
+    let shader = generate_or_use_handle(handle, Some("material"));
+
+    ctx.create(shader.as_str(), nsi::NodeType::Shader, &[]);
+    ctx.set_attribute(
+        shader.as_str(),
+        &[nsi::string!("shaderfilename", shader_file_name)],
+    );
+    ctx.set_attribute(shader.as_str(), attributes);
+
+    if !args.is_empty() {
+        ctx.set_attribute(shader.as_str(), args);
+    }
+
+    shader
+}
+
+/// Creates a physically based material.
+///
+/// This uses the `dlPrincipled` shader that ships with 3Delight.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `base_color` – The diffuse color of dielectrics or the specular
+///   color of metals.
+///
+/// * `roughness` – From `0` (mirror-like) to `1` (matte).
+///
+/// * `metallic` – From `0` (dielectric, e.g. plastic) to `1` (metal).
+///
+/// * `translucency` – How much light passes through thin geometry,
+///   from `0` (none) to `1`, e.g. to backlight leaves or paper. Use
+///   together with [`set_double_sided()`](crate::set_double_sided()).
+///   If [`None`], `translucency` is not set.
+///
+/// Returns the handle of the created `shader`. Set e.g. the specular
+/// level, coating or sheen via `args`.
+///
+/// Use [`assign_material()`] to assign the shader to geometry.
+pub fn principled_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    base_color: [f32; 3],
+    roughness: f32,
+    metallic: f32,
+    translucency: Option<f32>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let shader = material(
+        ctx,
+        handle,
+        "${DELIGHT}/osl/dlPrincipled",
+        &[
+            nsi::color!("i_color", &base_color),
+            nsi::float!("roughness", roughness),
+            nsi::float!("metallic", metallic),
+        ],
+        &[],
+    );
+
+    if let Some(translucency) = translucency {
+        ctx.set_attribute(
+            shader.as_str(),
+            &[nsi::float!("translucency", translucency.clamp(0.0, 1.0))],
+        );
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(shader.as_str(), args);
+    }
+
+    shader
+}
+
+/// The index of refraction a [`glass_material()`] falls back to.
+pub const GLASS_IOR: f32 = 1.5;
+
+/// Creates a glass material.
+///
+/// This uses the `dlGlass` shader that ships with 3Delight.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `color` – The color of the light refracted through the glass.
+///
+/// * `ior` – The index of refraction. Typical glass is `1.5`, water
+///   `1.33` and diamond `2.42`. Non-physical values, i.e. less than
+///   `1`, are replaced with [`GLASS_IOR`].
+///
+/// * `roughness` – From `0` (clear) to `1` (frosted).
+///
+/// * `thin` – Treats the surface as an infinitely thin sheet. Light
+///   passes through without being bent, as is the case for e.g. a
+///   window pane or a soap bubble. This also means the geometry does
+///   not need to be closed.
+///
+/// Returns the handle of the created `shader`.
+pub fn glass_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    color: [f32; 3],
+    ior: f32,
+    roughness: f32,
+    thin: bool,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    // Also catches NaN.
+    let ior = if ior >= 1.0 { ior } else { GLASS_IOR };
+
+    material(
+        ctx,
+        handle,
+        "${DELIGHT}/osl/dlGlass",
+        &[
+            nsi::color!("refract_color", &color),
+            nsi::float!("refract_ior", ior),
+            nsi::float!("refract_roughness", roughness),
+            nsi::float!("reflect_ior", ior),
+            nsi::float!("reflect_roughness", roughness),
+            nsi::integer!("thin_walled", thin as _),
+        ],
+        args,
+    )
+}
+
+/// The base color and edge tint of the [`Metal`] presets, in this
+/// order: gold, silver, copper, aluminium and iron.
+///
+/// The colors are linear (not sRGB).
+pub const METAL_COLORS: [([f32; 3], [f32; 3]); 5] = [
+    ([1.000, 0.782, 0.344], [0.996, 0.955, 0.777]),
+    ([0.972, 0.960, 0.915], [0.996, 0.993, 0.986]),
+    ([0.955, 0.638, 0.538], [0.996, 0.909, 0.818]),
+    ([0.913, 0.922, 0.924], [0.980, 0.982, 0.985]),
+    ([0.562, 0.565, 0.578], [0.885, 0.884, 0.893]),
+];
+
+/// A metal for [`metal_material()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metal {
+    /// Warm yellow, e.g. for jewelry or electronics contacts.
+    Gold,
+    /// The most reflective metal, slightly warm.
+    Silver,
+    /// Reddish orange, e.g. for pipes or cookware.
+    Copper,
+    /// A bright, neutral grey, e.g. for brushed panels.
+    Aluminium,
+    /// A dark, neutral grey, e.g. for raw or machined steel.
+    Iron,
+    /// A custom base color, in linear space. The edge tint is white.
+    Custom([f32; 3]),
+}
+
+impl Metal {
+    /// Returns the base color and edge tint of the metal. See
+    /// [`METAL_COLORS`].
+    pub fn colors(&self) -> ([f32; 3], [f32; 3]) {
+        match self {
+            Metal::Gold => METAL_COLORS[0],
+            Metal::Silver => METAL_COLORS[1],
+            Metal::Copper => METAL_COLORS[2],
+            Metal::Aluminium => METAL_COLORS[3],
+            Metal::Iron => METAL_COLORS[4],
+            Metal::Custom(color) => (*color, [1.0; 3]),
+        }
+    }
+}
+
+/// Creates a metal material.
+///
+/// This uses the `dlPrincipled` shader that ships with 3Delight with
+/// `metallic` set to `1`.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `metal` – The kind of metal. This sets the base color and the
+///   tint of the reflections at grazing angles.
+///
+/// * `roughness` – From `0` (polished) to `1` (brushed/matte).
+///
+/// Returns the handle of the created `shader`.
+pub fn metal_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    metal: Metal,
+    roughness: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let (base_color, edge_tint) = metal.colors();
+
+    material(
+        ctx,
+        handle,
+        "${DELIGHT}/osl/dlPrincipled",
+        &[
+            nsi::color!("i_color", &base_color),
+            nsi::color!("edge_color", &edge_tint),
+            nsi::float!("roughness", roughness),
+            nsi::float!("metallic", 1.0),
+        ],
+        args,
+    )
+}
+
+/// Creates an emissive material.
+///
+/// This uses the `areaLight` shader that ships with 3Delight. It
+/// emits a constant radiance and reflects nothing.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `color` – The color of the emitted light.
+///
+/// * `intensity` – Scales the `color`. This is radiance, i.e. a
+///   surface facing the camera with a `color` of `[1, 1, 1]` and an
+///   `intensity` of `1` renders as a pixel value of `1`. Any exposure
+///   applied to the image scales this like all other light.
+///
+/// Returns the handle of the created `shader`.
+///
+/// Geometry with this material also lights the scene. I.e. assigning
+/// it makes the geometry a light, like [`mesh_light()`](crate::mesh_light()).
+pub fn emissive_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    color: [f32; 3],
+    intensity: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    material(
+        ctx,
+        handle,
+        "${DELIGHT}/osl/areaLight",
+        &[
+            nsi::float!("intensity", intensity),
+            nsi::color!("i_color", &color),
+        ],
+        args,
+    )
+}
+
+/// Creates a subsurface scattering material, e.g. for skin or wax.
+///
+/// This uses the `dlPrincipled` shader that ships with 3Delight.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `color` – The color of the surface.
+///
+/// * `radius` – The mean free path, per red, green and blue channel,
+///   in millimeters. I.e. how far light of each color travels below
+///   the surface before it is scattered. For skin red travels
+///   furthest, e.g. `[3.67, 1.37, 0.68]`.
+///
+/// * `scale` – Scales the `radius`. Use this to account for the
+///   scale of the scene – e.g. `0.1` if the scene is in centimeters.
+///   Negative values are clamped to `0`.
+///
+/// Returns the handle of the created `shader`.
+pub fn sss_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    color: [f32; 3],
+    radius: [f32; 3],
+    scale: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    material(
+        ctx,
+        handle,
+        "${DELIGHT}/osl/dlPrincipled",
+        &[
+            nsi::color!("i_color", &color),
+            nsi::float!("sss_weight", 1.0),
+            nsi::color!("sss_color", &color),
+            nsi::color!("sss_radius", &radius),
+            // Also catches NaN.
+            nsi::float!("sss_scale", scale.max(0.0)),
+        ],
+        args,
+    )
+}
+
+/// The contour outlines of a [`toon_material()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutlineSpec {
+    /// The color of the outlines.
+    pub color: [f32; 3],
+    /// The width of the outlines, in pixels.
+    pub width: f32,
+}
+
+/// Creates a toon (cel-shaded) material.
+///
+/// This uses the `dlToon` shader that ships with 3Delight.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `base_color` – The color of the lit surface.
+///
+/// * `outline` – Draws contour outlines around the geometry. If
+///   [`None`], no contour attributes are set at all and outlines do
+///   not cost any render time.
+///
+/// Returns the handle of the created `shader`.
+pub fn toon_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    base_color: [f32; 3],
+    outline: Option<OutlineSpec>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let shader = material(
+        ctx,
+        handle,
+        "${DELIGHT}/osl/dlToon",
+        &[nsi::color!("i_color", &base_color)],
+        &[],
+    );
+
+    if let Some(outline) = outline {
+        ctx.set_attribute(
+            shader.as_str(),
+            &[
+                nsi::integer!("contour_enable", 1),
+                nsi::color!("contour_color", &outline.color),
+                nsi::float!("contour_width", outline.width),
+            ],
+        );
+    }
+
+    if !args.is_empty() {
+        ctx.set_attribute(shader.as_str(), args);
+    }
+
+    shader
+}
+
+/// Creates a hair and fur material.
+///
+/// This uses the `dlHairAndFur` shader that ships with 3Delight. It
+/// is meant to be assigned to [`curves()`](crate::curves()).
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `tint` – A dye color the hair is multiplied with. Use white for
+///   natural hair.
+///
+/// * `melanin` – The amount of pigment in the hair, from `0` (white)
+///   to `1` (black). Blonde hair is around `0.2`, brown around `0.6`.
+///
+/// * `roughness` – From `0` (smooth, shiny) to `1` (rough, dull).
+///
+/// Returns the handle of the created `shader`.
+///
+/// The balance between the brown/black eumelanin and the red
+/// pheomelanin can be set via the `melanin_red` attribute in `args`,
+/// from `0` (no red) to `1` (all red). Also pass
+/// `roughness_longitudinal` or `roughness_azimuthal` there to set the
+/// two roughnesses separately.
+pub fn hair_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    tint: [f32; 3],
+    melanin: f32,
+    roughness: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    material(
+        ctx,
+        handle,
+        "${DELIGHT}/osl/dlHairAndFur",
+        &[
+            nsi::color!("dye_color", &tint),
+            nsi::float!("melanin", melanin),
+            nsi::float!("roughness", roughness),
+        ],
+        args,
+    )
+}
+
+/// Turns existing geometry into a shadow catcher.
+///
+/// This is used to composite renders over a back plate. The geometry
+/// stands in for e.g. the floor in the plate. It is not rendered
+/// itself but it receives the shadows and reflections of the other
+/// objects.
+///
+/// # Arguments
+/// * `geometry` – The handle of the geometry to make a shadow catcher.
+///
+/// Returns the handle of the created `dlShadowMatte` `shader`.
+///
+/// The geometry's `matte` attribute is set. In the beauty it thus
+/// holds out everything behind it and its color is black. The shadows
+/// and reflections it catches are written to the alpha channel to be
+/// composited *over* the plate. Where nothing falls onto it, the
+/// alpha is `0` and the plate shows through as-is.
+pub fn shadow_catcher<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    geometry: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let shader = material(ctx, None, "${DELIGHT}/osl/dlShadowMatte", &[], args);
+
+    assign_material_with_attributes(ctx, geometry, shader.as_str(), &[nsi::integer!("matte", 1)]);
+
+    shader
+}
+
+// Creates a shader node from inline OSL `source` and sets `attributes`
+// followed by `args` on it.
+pub(crate) fn source_shader<'a>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    prefix: &str,
+    source: &str,
+    attributes: &nsi::ArgSlice<'_, 'a>,
+    args: &nsi::ArgSlice<'_, 'a>,
+) -> String {
+    let shader = generate_or_use_handle(handle, Some(prefix));
+
+    ctx.create(shader.as_str(), nsi::NodeType::Shader, &[]);
+    ctx.set_attribute(shader.as_str(), &[nsi::string!("shadersource", source)]);
+    ctx.set_attribute(shader.as_str(), attributes);
+
+    if !args.is_empty() {
+        ctx.set_attribute(shader.as_str(), args);
+    }
+
+    shader
+}
+
+// Projects a texture along the three world axes and blends the
+// projections with the shading normal.
+const TRIPLANAR_TEXTURE_SOURCE: &str = r#"
+shader triplanarTexture(
+    string textureFile = "",
+    float scale = 1,
+    float blend = 4,
+    output color outColor = 0,
+)
+{
+    point p = scale * transform("world", P);
+    normal n = normalize(transform("world", N));
+
+    float wx = pow(abs(n[0]), blend);
+    float wy = pow(abs(n[1]), blend);
+    float wz = pow(abs(n[2]), blend);
+
+    color x = texture(textureFile, p[2], 1 - p[1]);
+    color y = texture(textureFile, p[0], 1 - p[2]);
+    color z = texture(textureFile, p[0], 1 - p[1]);
+
+    outColor = (wx * x + wy * y + wz * z) / (wx + wy + wz);
+}
+"#;
+
+/// Creates a texture that is projected along the X, Y and Z axes.
+///
+/// This textures geometry that has no UVs. Each point is looked up
+/// in three planar projections which are blended by how much the
+/// shading normal faces each axis.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `texture` – The image to project.
+///
+/// * `scale` – How often the texture repeats per scene unit.
+///
+/// * `blend` – The sharpness of the transitions between projections.
+///   `1` blends linearly with the normal, higher values give narrower
+///   seams. Values below `1` are clamped to `1`.
+///
+/// The projection uses world space. The texture thus stays in place
+/// when objects move through it. Divide `scale` by an object's size
+/// to give objects of different sizes the same number of repeats.
+///
+/// Returns the handle of the `shader`. The `shader` is compiled from
+/// OSL source on the fly. Its `outColor` is meant to be connected to
+/// a material's color, e.g. the `i_color` of a
+/// [`principled_material()`].
+pub fn triplanar_texture<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    texture: &str,
+    scale: f32,
+    blend: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    source_shader(
+        ctx,
+        handle,
+        "triplanar_texture",
+        TRIPLANAR_TEXTURE_SOURCE,
+        &[
+            nsi::string!("textureFile", texture),
+            nsi::float!("scale", scale),
+            nsi::float!("blend", blend.max(1.0)),
+        ],
+        args,
+    )
+}
+
+// Applies gamma, then gain, then saturation to `i_color`.
+const COLOR_CORRECT_SOURCE: &str = r#"
+shader colorCorrect(
+    color i_color = 1,
+    color gain = 1,
+    float gamma = 1,
+    float saturation = 1,
+    output color outColor = 0,
+)
+{
+    color c = gain * pow(max(i_color, color(0)), 1 / gamma);
+
+    outColor = mix(color(luminance(c)), c, saturation);
+}
+"#;
+
+/// Creates a color correction behind a shader.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `input` – The handle of the shader to correct. Its `outColor` is
+///   connected to the correction, e.g. of a [`triplanar_texture()`]
+///   or a `dlTexture`.
+///
+/// * `gain` – Multiplies the color, per channel. `1` leaves it as is.
+///
+/// * `gamma` – Raises the color to `1 / gamma`. Values above `1`
+///   brighten mid-tones, values below darken them. This is applied
+///   *before* `gain`. Values below `0.001` are clamped to `0.001`.
+///
+/// * `saturation` – `0` gives grey, `1` leaves the color as is and
+///   values above `1` make it more colorful.
+///
+/// Returns the handle of the `shader`. ɴsɪ can not tell what is
+/// connected to `input` already so it is up to the user to connect
+/// its `outColor` instead, e.g. to a material's `i_color`. The
+/// `shader` is compiled from OSL source on the fly.
+pub fn color_correct<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    input: &str,
+    gain: [f32; 3],
+    gamma: f32,
+    saturation: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let shader = source_shader(
+        ctx,
+        handle,
+        "color_correct",
+        COLOR_CORRECT_SOURCE,
+        &[
+            nsi::color!("gain", &gain),
+            nsi::float!("gamma", gamma.max(1.0e-3)),
+            nsi::float!("saturation", saturation),
+        ],
+        args,
+    );
+
+    ctx.connect(input, "outColor", shader.as_str(), "i_color", &[]);
+
+    shader
+}
+
+// Alternates `color_a` and `color_b` in a grid in UV space.
+const CHECKER_TEXTURE_SOURCE: &str = r#"
+shader checkerTexture(
+    color color_a = 0,
+    color color_b = 1,
+    float scale = 1,
+    output color outColor = 0,
+)
+{
+    int check = (int) (floor(scale * u) + floor(scale * v));
+
+    outColor = (0 == check % 2) ? color_a : color_b;
+}
+"#;
+
+// Fractal Perlin noise in UV space, remapped to 0..1.
+const NOISE_TEXTURE_SOURCE: &str = r#"
+shader noiseTexture(
+    float frequency = 1,
+    int octaves = 4,
+    output color outColor = 0,
+)
+{
+    point p = frequency * point(u, v, 0);
+    float sum = 0;
+    float total = 0;
+    float amplitude = 1;
+
+    for (int i = 0; i < octaves; ++i) {
+        sum += amplitude * noise("perlin", p);
+        total += amplitude;
+        amplitude *= 0.5;
+        p *= 2;
+    }
+
+    outColor = 0.5 + 0.5 * sum / total;
+}
+"#;
+
+/// Creates a checkerboard texture.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `color_a`, `color_b` – The colors of the squares. The square at
+///   the UV origin has `color_a`.
+///
+/// * `scale` – The number of squares per UV unit, along U and V.
+///
+/// The pattern is laid out in the surface's UV space, i.e. OSL's `u`
+/// and `v`. A `scale` of `8` gives a chess board on a surface with
+/// UVs in `0..1`.
+///
+/// Returns the handle of the `shader`. Connect its `outColor` to a
+/// material's color, e.g. the `i_color` of a
+/// [`principled_material()`]. The `shader` is compiled from OSL
+/// source on the fly.
+pub fn checker_texture<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    color_a: [f32; 3],
+    color_b: [f32; 3],
+    scale: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    source_shader(
+        ctx,
+        handle,
+        "checker_texture",
+        CHECKER_TEXTURE_SOURCE,
+        &[
+            nsi::color!("color_a", &color_a),
+            nsi::color!("color_b", &color_b),
+            nsi::float!("scale", scale),
+        ],
+        args,
+    )
+}
+
+/// Creates a grey fractal noise texture.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `frequency` – The number of noise features per UV unit of the
+///   first octave.
+///
+/// * `octaves` – The number of layers of noise. Each one has twice the
+///   frequency and half the amplitude of the one before. Values below
+///   `1` are clamped to `1`.
+///
+/// The noise is computed in the surface's UV space, i.e. OSL's `u` and
+/// `v`, and lies in `0..1`.
+///
+/// Returns the handle of the `shader`. Connect its `outColor` to a
+/// material's color. The `shader` is compiled from OSL source on the
+/// fly.
+pub fn noise_texture<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    frequency: f32,
+    octaves: i32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    source_shader(
+        ctx,
+        handle,
+        "noise_texture",
+        NOISE_TEXTURE_SOURCE,
+        &[
+            nsi::float!("frequency", frequency),
+            nsi::integer!("octaves", octaves.max(1)),
+        ],
+        args,
+    )
+}
+
+/// Displaces geometry with a shader.
+///
+/// # Arguments
+/// * `geometry` – The handle of the geometry or of a transform above
+///   it.
+///
+/// * `shader` – The handle of the displacement shader, e.g. as
+///   returned by [`displacement_texture()`].
+///
+/// * `bound` – How far, at most, the shader moves points, in object
+///   space. This is set as `displacementbound.sphere`.
+///
+/// The renderer only looks for displaced geometry within the bound.
+/// Parts that are displaced further than it are cut off. Bounds that
+/// are much too large make rendering slow.
+///
+/// The shader is connected to the `displacementshader` of the
+/// `attributes` node of `geometry`, with the handle
+/// `<geometry>_attributes`. It is created and connected to the
+/// `geometryattributes` of `geometry` if it does not exist yet.
+/// Setting another displacement replaces the previous one.
+pub fn set_displacement(ctx: &nsi::Context, geometry: &str, shader: &str, bound: f32) {
+    let attributes = geometry_attributes(ctx, geometry);
+
+    ctx.set_attribute(
+        attributes.as_str(),
+        &[nsi::float!("displacementbound.sphere", bound)],
+    );
+    ctx.disconnect(".all", "", attributes.as_str(), "displacementshader");
+    ctx.append(attributes.as_str(), Some("displacementshader"), shader);
+}
+
+// Moves points along the normal by the value of a height map.
+const DISPLACEMENT_TEXTURE_SOURCE: &str = r#"
+displacement heightDisplacement(
+    string textureFile = "",
+    float scale = 1,
+)
+{
+    float height = texture(textureFile, u, 1 - v);
+
+    P += scale * height * normalize(N);
+    N = calculatenormal(P);
+}
+"#;
+
+/// Creates a displacement shader from a height map.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `texture` – The height map. Its first channel is read in the
+///   surface's UV space. Height maps are data, so make sure they are
+///   stored linearly and not as sRGB.
+///
+/// * `scale` – How far a height of `1` moves a point along its
+///   normal, in object space.
+///
+/// Returns the handle of the `shader`. Hook it up with
+/// [`set_displacement()`] and a `bound` of at least `scale` times the
+/// largest height in the map. The `shader` is compiled from OSL source
+/// on the fly.
+pub fn displacement_texture<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    texture: &str,
+    scale: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    source_shader(
+        ctx,
+        handle,
+        "displacement_texture",
+        DISPLACEMENT_TEXTURE_SOURCE,
+        &[
+            nsi::string!("textureFile", texture),
+            nsi::float!("scale", scale),
+        ],
+        args,
+    )
+}
+
+/// The space the normals of a [`normal_map()`] are stored in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalSpace {
+    /// Relative to the surface, with the UV tangents along X and Y and
+    /// the normal along Z. These maps look mostly blue.
+    #[default]
+    Tangent,
+    /// In the object space of the geometry. These maps look like a
+    /// rainbow.
+    Object,
+}
+
+// Reads a normal from a texture and blends it with the shading normal.
+const NORMAL_MAP_SOURCE: &str = r#"
+shader normalMap(
+    string textureFile = "",
+    int tangent_space = 1,
+    float strength = 1,
+    output normal outNormal = 0,
+)
+{
+    color c = texture(textureFile, u, 1 - v);
+    vector m = vector(2 * c[0] - 1, 2 * c[1] - 1, 2 * c[2] - 1);
+
+    normal n;
+    if (tangent_space) {
+        vector bitangent = normalize(cross(N, dPdu));
+        vector tangent = cross(bitangent, N);
+        n = normalize(m[0] * tangent + m[1] * bitangent + m[2] * N);
+    } else {
+        n = normalize(transform("object", "common", normal(m)));
+    }
+
+    outNormal = normalize(mix(N, n, strength));
+}
+"#;
+
+/// Creates a shader that perturbs normals from a normal map.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `texture` – The normal map, read in the surface's UV space. Its
+///   channels are mapped from `0..1` to `-1..1`. Normal maps are data:
+///   they must be stored linearly and not be color managed.
+///
+/// * `strength` – Blends from the geometry's normal (`0`) to the
+///   mapped one (`1`).
+///
+/// * `space` – The space the normals are stored in.
+///
+/// Returns the handle of the `shader`. Connect its `outNormal` to a
+/// material's normal input. The `shader` is compiled from OSL source
+/// on the fly.
+pub fn normal_map<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    texture: &str,
+    strength: f32,
+    space: NormalSpace,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    source_shader(
+        ctx,
+        handle,
+        "normal_map",
+        NORMAL_MAP_SOURCE,
+        &[
+            nsi::string!("textureFile", texture),
+            nsi::integer!("tangent_space", (NormalSpace::Tangent == space) as _),
+            nsi::float!("strength", strength.clamp(0.0, 1.0)),
+        ],
+        args,
+    )
+}
+
+// Computes the normal a height map would give if it displaced points.
+const BUMP_MAP_SOURCE: &str = r#"
+shader bumpMap(
+    string textureFile = "",
+    float scale = 1,
+    output normal outNormal = 0,
+)
+{
+    float height = texture(textureFile, u, 1 - v);
+
+    outNormal = normalize(calculatenormal(P + scale * height * normalize(N)));
+}
+"#;
+
+/// Creates a shader that perturbs normals from a height map.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `texture` – The height map. Its first channel is read in the
+///   surface's UV space. Like normal maps, height maps are data and
+///   must be stored linearly.
+///
+/// * `scale` – The height a value of `1` corresponds to, in object
+///   space. Negative values invert the bumps.
+///
+/// Unlike [`displacement_texture()`] this only changes shading. The
+/// silhouette of the geometry stays the same.
+///
+/// Returns the handle of the `shader`. Connect its `outNormal` to a
+/// material's normal input. The `shader` is compiled from OSL source
+/// on the fly.
+pub fn bump_map<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    texture: &str,
+    scale: f32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    source_shader(
+        ctx,
+        handle,
+        "bump_map",
+        BUMP_MAP_SOURCE,
+        &[
+            nsi::string!("textureFile", texture),
+            nsi::float!("scale", scale),
+        ],
+        args,
+    )
+}
+
+// Blends two surface closures by a constant or a texture.
+const MIX_MATERIAL_SOURCE: &str = r#"
+shader mixMaterial(
+    closure color base = 0,
+    closure color top = 0,
+    float mask = 0,
+    string maskFile = "",
+    output closure color outColor = 0,
+)
+{
+    float m = mask;
+
+    if ("" != maskFile) {
+        m = texture(maskFile, u, 1 - v);
+    }
+
+    m = clamp(m, 0, 1);
+
+    outColor = (1 - m) * base + m * top;
+    Ci = outColor;
+}
+"#;
+
+/// How much of the `top` material [`mix_material()`] shows.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MixMask {
+    /// The same amount everywhere, from `0` (only `base`) to `1`
+    /// (only `top`).
+    Constant(f32),
+    /// The path of a texture whose first channel is read in the
+    /// surface's UV space. Like height maps, masks are data and must
+    /// be stored linearly.
+    Texture(String),
+}
+
+/// Layers one material over another, e.g. rust over metal or dust
+/// over paint.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `base`, `top` – The handles of the two surface shaders, e.g. as
+///   returned by [`principled_material()`] or [`metal_material()`].
+///   Their `outColor` closures are connected to the mix. Both must
+///   be surface shaders; textures or other patterns do not work.
+///
+/// * `mask` – Where `top` is shown. The mask is clamped to `0..1`.
+///
+/// The result is a linear interpolation of the two closures, i.e.
+/// `(1 - mask) * base + mask * top`. Both materials are evaluated
+/// wherever the mask is not exactly `0` or `1`. An in-between value
+/// does not give a layer of `top` lying *on* `base`; the two are
+/// blended as if each covered part of the surface.
+///
+/// Returns the handle of the `shader`. Assign it with
+/// [`assign_material()`] instead of `base` and `top`, or use it as
+/// the `base` or `top` of another mix. The `shader` is compiled
+/// from OSL source on the fly.
+pub fn mix_material<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    base: &str,
+    top: &str,
+    mask: MixMask,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let shader = source_shader(
+        ctx,
+        handle,
+        "mix_material",
+        MIX_MATERIAL_SOURCE,
+        &[match &mask {
+            MixMask::Constant(mask) => nsi::float!("mask", *mask),
+            MixMask::Texture(texture) => nsi::string!("maskFile", texture.as_str()),
+        }],
+        args,
+    );
+
+    ctx.connect(base, "outColor", shader.as_str(), "base", &[]);
+    ctx.connect(top, "outColor", shader.as_str(), "top", &[]);
+
+    shader
+}
@@ -0,0 +1,260 @@
+// Small vector/matrix helpers.
+//
+// Matrices are `[f64; 16]` in the memory layout ɴsɪ expects: the
+// first three rows are the images of the X, Y and Z axes and the
+// translation is in elements 12–14.
+
+pub(crate) const IDENTITY: [f64; 16] = [
+    1.0, 0.0, 0.0, 0.0, //
+    0.0, 1.0, 0.0, 0.0, //
+    0.0, 0.0, 1.0, 0.0, //
+    0.0, 0.0, 0.0, 1.0,
+];
+
+#[inline]
+pub(crate) fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[inline]
+pub(crate) fn length(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+#[inline]
+pub(crate) fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let length = length(v);
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+// Builds a matrix from three axes and a translation.
+#[inline]
+pub(crate) fn from_axes(x: [f64; 3], y: [f64; 3], z: [f64; 3], t: [f64; 3]) -> [f64; 16] {
+    [
+        x[0], x[1], x[2], 0.0, //
+        y[0], y[1], y[2], 0.0, //
+        z[0], z[1], z[2], 0.0, //
+        t[0], t[1], t[2], 1.0,
+    ]
+}
+
+// Returns a rotation that takes the +Z axis to `direction`.
+//
+// `direction` does not need to be normalized.
+pub(crate) fn align_z(direction: [f64; 3]) -> [f64; 16] {
+    let z = normalize(direction);
+    // Avoid a degenerate basis if `z` is (almost) parallel to Y.
+    let up = if z[1].abs() < 0.999 {
+        [0.0, 1.0, 0.0]
+    } else {
+        [1.0, 0.0, 0.0]
+    };
+    let x = normalize(cross(up, z));
+    let y = cross(z, x);
+
+    from_axes(x, y, z, [0.0; 3])
+}
+
+// Returns a rotation of `angle` degrees around `axis`.
+//
+// The rotation is counter-clockwise when looking down `axis` towards
+// the origin. `axis` does not need to be normalized.
+pub(crate) fn rotation(angle: f64, axis: [f64; 3]) -> [f64; 16] {
+    let [x, y, z] = normalize(axis);
+    let (s, c) = angle.to_radians().sin_cos();
+    let t = 1.0 - c;
+
+    from_axes(
+        [c + x * x * t, y * x * t + z * s, z * x * t - y * s],
+        [x * y * t - z * s, c + y * y * t, z * y * t + x * s],
+        [x * z * t + y * s, y * z * t - x * s, c + z * z * t],
+        [0.0; 3],
+    )
+}
+
+// Returns the transform that applies `a` followed by `b`.
+pub(crate) fn multiply(a: &[f64; 16], b: &[f64; 16]) -> [f64; 16] {
+    let mut m = [0.0; 16];
+    for row in 0..4 {
+        for column in 0..4 {
+            m[4 * row + column] = (0..4).map(|i| a[4 * row + i] * b[4 * i + column]).sum();
+        }
+    }
+    m
+}
+
+// Returns a translation by `offset`.
+#[inline]
+pub(crate) fn translation(offset: [f64; 3]) -> [f64; 16] {
+    from_axes([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], offset)
+}
+
+// Returns a scale by `factors` along the X, Y and Z axes.
+#[inline]
+pub(crate) fn scale(factors: [f64; 3]) -> [f64; 16] {
+    from_axes(
+        [factors[0], 0.0, 0.0],
+        [0.0, factors[1], 0.0],
+        [0.0, 0.0, factors[2]],
+        [0.0; 3],
+    )
+}
+
+// Returns a transform placing the origin at `eye` with the -Z axis
+// pointing towards `target`.
+//
+// If `up` is (almost) parallel to the view direction, the Z or X axis
+// is used as the up vector instead.
+pub(crate) fn look_at(eye: [f64; 3], target: [f64; 3], up: [f64; 3]) -> [f64; 16] {
+    let z = normalize([eye[0] - target[0], eye[1] - target[1], eye[2] - target[2]]);
+
+    let mut x = cross(up, z);
+    if length(x) <= 1.0e-6 * length(up) {
+        let up = if z[2].abs() < 0.999 {
+            [0.0, 0.0, 1.0]
+        } else {
+            [1.0, 0.0, 0.0]
+        };
+        x = cross(up, z);
+    }
+    let x = normalize(x);
+    let y = cross(z, x);
+
+    from_axes(x, y, z, eye)
+}
+
+// Returns the rotation described by the quaternion `[w, x, y, z]`.
+//
+// The quaternion does not need to be normalized. A zero-length
+// quaternion yields the identity.
+pub(crate) fn from_quaternion(quat: [f64; 4]) -> [f64; 16] {
+    let length = (quat[0] * quat[0] + quat[1] * quat[1] + quat[2] * quat[2] + quat[3] * quat[3])
+        .sqrt();
+    if length == 0.0 {
+        return IDENTITY;
+    }
+    let [w, x, y, z] = [
+        quat[0] / length,
+        quat[1] / length,
+        quat[2] / length,
+        quat[3] / length,
+    ];
+
+    from_axes(
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y + w * z),
+            2.0 * (x * z - w * y),
+        ],
+        [
+            2.0 * (x * y - w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z + w * x),
+        ],
+        [
+            2.0 * (x * z + w * y),
+            2.0 * (y * z - w * x),
+            1.0 - 2.0 * (x * x + y * y),
+        ],
+        [0.0; 3],
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Applies `m` to the point `p`.
+    pub(crate) fn transform_point(m: &[f64; 16], p: [f64; 3]) -> [f64; 3] {
+        let mut q = [m[12], m[13], m[14]];
+        for (row, coordinate) in p.iter().enumerate() {
+            for (column, q) in q.iter_mut().enumerate() {
+                *q += coordinate * m[4 * row + column];
+            }
+        }
+        q
+    }
+
+    pub(crate) fn assert_close(expected: [f64; 3], actual: [f64; 3]) {
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert!(
+                (expected - actual).abs() < 1.0e-9,
+                "expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn rotation_is_in_degrees() {
+        let quarter_turn = rotation(90.0, [0.0, 1.0, 0.0]);
+
+        assert_close(
+            [0.0, 0.0, -1.0],
+            transform_point(&quarter_turn, [1.0, 0.0, 0.0]),
+        );
+        assert_close(
+            [1.0, 0.0, 0.0],
+            transform_point(&quarter_turn, [0.0, 0.0, 1.0]),
+        );
+        assert_close(
+            [0.0, 1.0, 0.0],
+            transform_point(&quarter_turn, [0.0, 1.0, 0.0]),
+        );
+    }
+
+    // Returns the direction the -Z axis of `m` points in.
+    fn view_direction(m: &[f64; 16]) -> [f64; 3] {
+        [-m[8], -m[9], -m[10]]
+    }
+
+    #[test]
+    fn look_at_aims_negative_z_at_target() {
+        let m = look_at([1.0, 2.0, 3.0], [4.0, 6.0, 3.0], [0.0, 1.0, 0.0]);
+
+        assert_close([0.6, 0.8, 0.0], view_direction(&m));
+        assert_close([1.0, 2.0, 3.0], transform_point(&m, [0.0; 3]));
+    }
+
+    #[test]
+    fn look_at_along_up() {
+        let m = look_at([0.0; 3], [0.0, 5.0, 0.0], [0.0, 1.0, 0.0]);
+
+        assert_close([0.0, 1.0, 0.0], view_direction(&m));
+        for axis in 0..3 {
+            assert!((length([m[4 * axis], m[4 * axis + 1], m[4 * axis + 2]]) - 1.0).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn quaternion_matches_axis_angle() {
+        for &(angle, axis) in &[
+            (90.0, [0.0, 1.0, 0.0]),
+            (180.0, [1.0, 0.0, 0.0]),
+            (45.0, [1.0, 1.0, 0.0]),
+            (-30.0, [0.0, 0.0, 2.0]),
+        ] {
+            let [x, y, z] = normalize(axis);
+            let (s, c) = (0.5 * f64::to_radians(angle)).sin_cos();
+            let quaternion = from_quaternion([c, s * x, s * y, s * z]);
+
+            for (quaternion, axis_angle) in quaternion.iter().zip(rotation(angle, axis).iter()) {
+                assert!((quaternion - axis_angle).abs() < 1.0e-12, "{:?}", axis);
+            }
+        }
+    }
+
+    #[test]
+    fn quaternion_is_normalized() {
+        assert_eq!(
+            from_quaternion([0.0, 0.0, 1.0, 0.0]),
+            from_quaternion([0.0, 0.0, 3.0, 0.0])
+        );
+        assert_eq!(IDENTITY, from_quaternion([0.0; 4]));
+    }
+}
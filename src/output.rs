@@ -0,0 +1,662 @@
+//! Shortcuts for setting up the output pipeline.
+//!
+//! Output flows from a camera through a `screen` into one or more
+//! `outputlayer`s which are in turn connected to `outputdriver`s.
+use nsi::toolbelt::generate_or_use_handle;
+
+/// Creates a screen and connects it to a camera.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `camera` – The handle of the camera to rasterize.
+///
+/// * `resolution` – The size of the image in pixels, as
+///   `(width, height)`.
+///
+/// * `oversampling` – The number of camera rays per pixel.
+///
+/// Use `args` to set e.g. `pixelaspectratio` or `crop`.
+///
+/// Returns the handle of the screen.
+pub fn screen<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    camera: &str,
+    resolution: (i32, i32),
+    oversampling: i32,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let screen = generate_or_use_handle(handle, Some("screen"));
+
+    ctx.create(screen.as_str(), nsi::NodeType::Screen, &[]);
+    ctx.append(camera, Some("screens"), screen.as_str());
+
+    ctx.set_attribute(
+        screen.as_str(),
+        &[
+            nsi::integers!("resolution", &[resolution.0, resolution.1]).array_len(2),
+            nsi::integer!("oversampling", oversampling),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(screen.as_str(), args);
+    }
+
+    screen
+}
+
+/// Restricts rendering of a screen to a sub-region.
+///
+/// # Arguments
+/// * `screen` – The handle of a screen, e.g. as returned by
+///   [`screen()`].
+///
+/// * `min`, `max` – The top-left and bottom-right corners of the
+///   region, in normalized screen coordinates. Both are clamped to
+///   `0.0..=1.0`.
+///
+/// # Errors
+/// If any of `min` or `max` is not finite or if, after clamping, `min`
+/// is not less than `max` along both axes.
+pub fn set_crop_window(
+    ctx: &nsi::Context,
+    screen: &str,
+    min: [f64; 2],
+    max: [f64; 2],
+) -> Result<(), crate::NsiHelperError> {
+    for value in &min {
+        crate::check_finite("min", *value)?;
+    }
+    for value in &max {
+        crate::check_finite("max", *value)?;
+    }
+
+    let min = [min[0].clamp(0.0, 1.0), min[1].clamp(0.0, 1.0)];
+    let max = [max[0].clamp(0.0, 1.0), max[1].clamp(0.0, 1.0)];
+
+    if !(min[0] < max[0] && min[1] < max[1]) {
+        return Err(crate::NsiHelperError::OutOfRange("max"));
+    }
+
+    ctx.set_attribute(
+        screen,
+        &[nsi::floats!(
+            "crop",
+            &[min[0] as f32, min[1] as f32, max[0] as f32, max[1] as f32]
+        )
+        .array_len(2)],
+    );
+
+    Ok(())
+}
+
+/// Extends the rendered region of a screen beyond its resolution.
+///
+/// # Arguments
+/// * `screen` – The handle of a screen, e.g. as returned by
+///   [`screen()`].
+///
+/// * `pixels` – The number of extra pixels, as
+///   `[left, top, right, bottom]`.
+pub fn set_overscan(ctx: &nsi::Context, screen: &str, pixels: [i32; 4]) {
+    ctx.set_attribute(screen, &[nsi::integers!("overscan", &pixels).array_len(2)]);
+}
+
+// Creates an output layer and connects it to `screen`.
+fn layer(ctx: &nsi::Context, handle: Option<&str>, screen: &str) -> String {
+    let layer = generate_or_use_handle(handle, Some("outputlayer"));
+
+    ctx.create(layer.as_str(), nsi::NodeType::OutputLayer, &[]);
+    ctx.append(screen, Some("outputlayers"), layer.as_str());
+
+    layer
+}
+
+/// Creates an output layer and connects it to a screen.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `variable` – The name of the variable to output, e.g. `"Ci"` for
+///   the beauty pass.
+///
+/// * `screen` – The handle of the screen to connect to.
+///
+/// The layer's `variablesource` is set to `"shader"` and its
+/// `scalarformat` to `"half"`. Use `args` to override these or to set
+/// e.g. `filter`, `filterwidth` or `withalpha`.
+///
+/// Returns the handle of the layer. Connect an output driver to this
+/// to get at the pixels.
+pub fn output_layer<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    variable: &str,
+    screen: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let layer = layer(ctx, handle, screen);
+
+    ctx.set_attribute(
+        layer.as_str(),
+        &[
+            nsi::string!("variablename", variable),
+            nsi::string!("variablesource", "shader"),
+            nsi::string!("scalarformat", "half"),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(layer.as_str(), args);
+    }
+
+    layer
+}
+
+/// Common arbitrary output variables (AOVs) 3Delight knows about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Aov {
+    /// The beauty pass (`Ci`).
+    Beauty,
+    /// The surface color, without any lighting.
+    Albedo,
+    /// The world space shading normal.
+    Normal,
+    /// The camera space depth.
+    Z,
+    /// The world space position.
+    Position,
+    /// Direct and indirect diffuse lighting.
+    Diffuse,
+    /// Direct and indirect specular lighting.
+    Specular,
+    /// Subsurface scattering.
+    Subsurface,
+    /// Reflections.
+    Reflection,
+    /// Refractions.
+    Refraction,
+    /// Light scattered by volumes.
+    Volume,
+    /// Light emitted by surfaces.
+    Emission,
+    /// Ambient occlusion.
+    Occlusion,
+    /// The screen space motion vector.
+    MotionVector,
+    /// The relative variance of each pixel's samples. Useful to judge
+    /// convergence.
+    RelativePixelVariance,
+    /// A shader output variable with the given name.
+    Custom(String),
+}
+
+impl Aov {
+    /// The layer's `variablename`.
+    pub fn variable_name(&self) -> &str {
+        match self {
+            Aov::Beauty => "Ci",
+            Aov::Albedo => "albedo",
+            Aov::Normal => "N.world",
+            Aov::Z => "z",
+            Aov::Position => "P.world",
+            Aov::Diffuse => "diffuse",
+            Aov::Specular => "specular",
+            Aov::Subsurface => "subsurface",
+            Aov::Reflection => "reflection",
+            Aov::Refraction => "refraction",
+            Aov::Volume => "volume",
+            Aov::Emission => "incandescence",
+            Aov::Occlusion => "occlusion",
+            Aov::MotionVector => "motionvector",
+            Aov::RelativePixelVariance => "relativevariance",
+            Aov::Custom(name) => name.as_str(),
+        }
+    }
+
+    /// The layer's `variablesource`.
+    pub fn variable_source(&self) -> &'static str {
+        match self {
+            Aov::Normal
+            | Aov::Z
+            | Aov::Position
+            | Aov::MotionVector
+            | Aov::RelativePixelVariance => "builtin",
+            _ => "shader",
+        }
+    }
+
+    /// The layer's `layertype`.
+    pub fn layer_type(&self) -> &'static str {
+        match self {
+            Aov::Z | Aov::RelativePixelVariance => "scalar",
+            Aov::Normal | Aov::Position | Aov::MotionVector => "vector",
+            _ => "color",
+        }
+    }
+
+    /// The layer's `scalarformat`.
+    ///
+    /// Data AOVs are stored as `"float"` and colors as `"half"`.
+    pub fn scalar_format(&self) -> &'static str {
+        match self.layer_type() {
+            "color" => "half",
+            _ => "float",
+        }
+    }
+}
+
+/// Creates an output layer for an [`Aov`] and connects it to a screen.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `aov` – The variable to output. This determines the layer's
+///   `variablename`, `variablesource`, `layertype` and
+///   `scalarformat`.
+///
+/// * `screen` – The handle of the screen to connect to.
+///
+/// Use `args` to override any of the above or to set e.g. `filter`,
+/// `filterwidth` or `withalpha`.
+///
+/// Returns the handle of the layer.
+pub fn output_layer_aov<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    aov: Aov,
+    screen: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let layer = layer(ctx, handle, screen);
+
+    ctx.set_attribute(
+        layer.as_str(),
+        &[
+            nsi::string!("variablename", aov.variable_name()),
+            nsi::string!("variablesource", aov.variable_source()),
+            nsi::string!("layertype", aov.layer_type()),
+            nsi::string!("scalarformat", aov.scalar_format()),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(layer.as_str(), args);
+    }
+
+    layer
+}
+
+/// The reconstruction filter of a layer. See [`set_pixel_filter()`].
+///
+/// The default is [`PixelFilter::BlackmanHarris`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelFilter {
+    /// Averages the samples in a square. Used with a width of `1` there
+    /// is no blurring across pixels but edges alias.
+    Box,
+    /// A tent that falls off linearly. Typically `2` wide.
+    Triangle,
+    /// Sharp, with a slight ringing at edges. Typically `3` wide.
+    CatmullRom,
+    /// Smooth with almost no ringing. Typically `3` wide.
+    #[default]
+    BlackmanHarris,
+    /// Soft; blurs some detail. Typically `2` to `3` wide.
+    Gaussian,
+    /// The sharpest, but rings strongly at edges. Typically `4` to `6`
+    /// wide.
+    Sinc,
+    /// A compromise between sharpness and ringing. Typically `4` wide.
+    Mitchell,
+}
+
+impl From<PixelFilter> for Vec<u8> {
+    #[inline]
+    fn from(filter: PixelFilter) -> Self {
+        match filter {
+            PixelFilter::Box => b"box".to_vec(),
+            PixelFilter::Triangle => b"triangle".to_vec(),
+            PixelFilter::CatmullRom => b"catmull-rom".to_vec(),
+            PixelFilter::BlackmanHarris => b"blackman-harris".to_vec(),
+            PixelFilter::Gaussian => b"gaussian".to_vec(),
+            PixelFilter::Sinc => b"sinc".to_vec(),
+            PixelFilter::Mitchell => b"mitchell".to_vec(),
+        }
+    }
+}
+
+/// The filter width 3Delight uses if none is set.
+pub const PIXEL_FILTER_WIDTH: f64 = 3.0;
+
+/// Sets how samples are filtered into the pixels of a layer.
+///
+/// # Arguments
+/// * `layer` – The handle of the layer, e.g. as returned by
+///   [`output_layer()`]. In ɴsɪ, the filter is an attribute of the
+///   layer so each layer can use a different one.
+///
+/// * `filter` – The filter. [`PixelFilter::default()`] gives
+///   Blackman-Harris which is a good choice for most renders.
+///
+/// * `width` – The diameter of the filter, in pixels. If unsure, use
+///   [`PIXEL_FILTER_WIDTH`].
+pub fn set_pixel_filter(ctx: &nsi::Context, layer: &str, filter: PixelFilter, width: f64) {
+    ctx.set_attribute(
+        layer,
+        &[
+            nsi::string!("filter", filter),
+            nsi::double!("filterwidth", width),
+        ],
+    );
+}
+
+// Creates an output driver and connects it to `layer`.
+fn driver(ctx: &nsi::Context, handle: Option<&str>, layer: &str, driver_name: &str) -> String {
+    let driver = generate_or_use_handle(handle, Some("outputdriver"));
+
+    ctx.create(driver.as_str(), nsi::NodeType::OutputDriver, &[]);
+    ctx.append(layer, Some("outputdrivers"), driver.as_str());
+
+    ctx.set_attribute(driver.as_str(), &[nsi::string!("drivername", driver_name)]);
+
+    driver
+}
+
+/// Creates an OpenEXR output driver and connects it to a layer.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `layer` – The handle of the layer to write.
+///
+/// * `file_path` – The name of the image file. This may contain
+///   tokens, e.g. `${DELIGHT}` or other `${...}` variables, which
+///   3Delight expands before writing the file.
+///
+/// Returns the handle of the driver.
+///
+/// To write several layers into one (multi-layer) file call this
+/// again with the same `handle` for each additional layer.
+pub fn output_driver_exr<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    layer: &str,
+    file_path: &str,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let driver = driver(ctx, handle, layer, "exr");
+
+    ctx.set_attribute(driver.as_str(), &[nsi::string!("imagefilename", file_path)]);
+
+    if !args.is_empty() {
+        ctx.set_attribute(driver.as_str(), args);
+    }
+
+    driver
+}
+
+/// Interactive targets an output layer can be displayed in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisplayTarget {
+    /// 3Delight Display (i-display).
+    IDisplay,
+    /// Houdini's MPlay/render view.
+    Houdini,
+    /// Any other output driver, by name.
+    Custom(String),
+}
+
+impl DisplayTarget {
+    /// The output driver's `drivername`.
+    pub fn driver_name(&self) -> &str {
+        match self {
+            DisplayTarget::IDisplay => "idisplay",
+            DisplayTarget::Houdini => "houdini",
+            DisplayTarget::Custom(name) => name.as_str(),
+        }
+    }
+}
+
+/// Creates an interactive display driver and connects it to a layer.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `layer` – The handle of the layer to display.
+///
+/// * `target` – Where to send the pixels.
+///
+/// Use `args` to set e.g. `imagefilename`, which most displays use
+/// as the title of the image.
+///
+/// Returns the handle of the driver.
+///
+/// Swapping this for an [`output_driver_exr()`] is all that is needed
+/// to go from look-dev to writing images to disk.
+pub fn output_driver_display<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    layer: &str,
+    target: DisplayTarget,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> String {
+    let driver = driver(ctx, handle, layer, target.driver_name());
+
+    if !args.is_empty() {
+        ctx.set_attribute(driver.as_str(), args);
+    }
+
+    driver
+}
+
+/// The handles of the guide layers created by [`enable_denoising()`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DenoiseHandles {
+    /// The albedo guide layer.
+    pub albedo: String,
+    /// The normal guide layer.
+    pub normal: String,
+}
+
+/// Enables denoising of a beauty layer.
+///
+/// Sets `denoise` on `beauty_layer` and creates the albedo and normal
+/// guide layers the denoiser needs on `screen`.
+///
+/// The guide layers get the handles `<screen>_denoise_albedo` and
+/// `<screen>_denoise_normal`. As re-creating an existing node with the
+/// same type is a no-op in ɴsɪ, calling this more than once for the
+/// same screen does not create duplicate guide layers.
+///
+/// Returns the [`DenoiseHandles`] of the guide layers. These need to
+/// be connected to the same output driver as `beauty_layer`.
+///
+/// ɴsɪ cannot be asked which layers a screen has. So the beauty layer
+/// has to be passed in and there is no telling if an earlier call
+/// already created the guide layers; their handles are returned
+/// either way.
+pub fn enable_denoising(ctx: &nsi::Context, screen: &str, beauty_layer: &str) -> DenoiseHandles {
+    ctx.set_attribute(beauty_layer, &[nsi::integer!("denoise", 1)]);
+
+    let albedo = output_layer_aov(
+        ctx,
+        Some((screen.to_string() + "_denoise_albedo").as_str()),
+        Aov::Albedo,
+        screen,
+        &[],
+    );
+
+    let normal = output_layer_aov(
+        ctx,
+        Some((screen.to_string() + "_denoise_normal").as_str()),
+        Aov::Normal,
+        screen,
+        &[],
+    );
+
+    DenoiseHandles { albedo, normal }
+}
+
+/// Where a bucket passed to an [`output_driver_callback()`] closure
+/// lies in the image.
+#[cfg(feature = "output")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BucketInfo {
+    /// The width of the whole image, in pixels.
+    pub image_width: usize,
+    /// The height of the whole image, in pixels.
+    pub image_height: usize,
+    /// The left edge of the bucket.
+    pub x_min: usize,
+    /// One past the right edge of the bucket.
+    pub x_max: usize,
+    /// The top edge of the bucket.
+    pub y_min: usize,
+    /// One past the bottom edge of the bucket.
+    pub y_max: usize,
+    /// The number of channels per pixel.
+    pub channels: usize,
+}
+
+#[cfg(feature = "output")]
+impl BucketInfo {
+    /// The width of the bucket, in pixels.
+    pub fn width(&self) -> usize {
+        self.x_max - self.x_min
+    }
+
+    /// The height of the bucket, in pixels.
+    pub fn height(&self) -> usize {
+        self.y_max - self.y_min
+    }
+}
+
+/// Creates an output driver that hands pixels to a closure and
+/// connects it to a layer.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `layer` – The handle of the layer to receive.
+///
+/// * `on_bucket` – Called once for each bucket as soon as the renderer
+///   has finished it.
+///
+///   The pixels are passed as `f32`s, whatever the `scalarformat` of
+///   the layer. They only cover the bucket described by the
+///   [`BucketInfo`]: `info.height()` rows of `info.width()` pixels
+///   each, top row first and left to right within a row. The
+///   `info.channels` channels of a pixel are interleaved, e.g. as
+///   `r, g, b, a`.
+///
+///   The slice is only valid for the duration of the call. Copy what
+///   you need to keep.
+///
+/// Returns the handle of the driver.
+///
+/// The closure is called from one of the renderer's threads, not from
+/// the one that started the render, but never concurrently for the
+/// same driver. It must not call back into `ctx`. As it may run after
+/// this returns it can not borrow anything that does not outlive the
+/// [`Context`](nsi::Context), and it must be [`Send`]. To get the
+/// pixels to e.g. a GUI thread, send them through a channel or an
+/// `Arc<Mutex<_>>`.
+///
+/// This uses the `ferris` display driver of the `nsi` crate and needs
+/// the `output` feature.
+#[cfg(feature = "output")]
+pub fn output_driver_callback<'a, F>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    layer: &str,
+    mut on_bucket: F,
+) -> String
+where
+    F: FnMut(&BucketInfo, &[f32]) + Send + 'a,
+{
+    let driver = driver(ctx, handle, layer, nsi::output::FERRIS);
+
+    // The renderer passes the whole image, updated with the new bucket,
+    // so copy the bucket out of it.
+    let mut bucket = Vec::new();
+    let write = nsi::output::WriteCallback::new(
+        move |_name: &str,
+              width: usize,
+              height: usize,
+              x_min: usize,
+              x_max_plus_one: usize,
+              y_min: usize,
+              y_max_plus_one: usize,
+              pixel_format: &nsi::output::PixelFormat,
+              pixel_data: &[f32]| {
+            let info = BucketInfo {
+                image_width: width,
+                image_height: height,
+                x_min,
+                x_max: x_max_plus_one,
+                y_min,
+                y_max: y_max_plus_one,
+                channels: pixel_format.channels(),
+            };
+
+            let row_length = info.width() * info.channels;
+            bucket.clear();
+            for y in y_min..y_max_plus_one {
+                let start = (y * width + x_min) * info.channels;
+                bucket.extend_from_slice(&pixel_data[start..start + row_length]);
+            }
+
+            on_bucket(&info, &bucket);
+
+            nsi::output::Error::None
+        },
+    );
+
+    ctx.set_attribute(
+        driver.as_str(),
+        &[
+            nsi::string!("imagefilename", driver.as_str()),
+            nsi::callback!("callback.write", write),
+        ],
+    );
+
+    driver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn z_aov_is_a_float() {
+        assert_eq!("z", Aov::Z.variable_name());
+        assert_eq!("builtin", Aov::Z.variable_source());
+        assert_eq!("scalar", Aov::Z.layer_type());
+        assert_eq!("float", Aov::Z.scalar_format());
+    }
+
+    #[test]
+    fn pixel_filter_names() {
+        for &(filter, name) in &[
+            (PixelFilter::Box, "box"),
+            (PixelFilter::Triangle, "triangle"),
+            (PixelFilter::CatmullRom, "catmull-rom"),
+            (PixelFilter::BlackmanHarris, "blackman-harris"),
+            (PixelFilter::Gaussian, "gaussian"),
+            (PixelFilter::Sinc, "sinc"),
+            (PixelFilter::Mitchell, "mitchell"),
+        ] {
+            assert_eq!(name.as_bytes(), Vec::<u8>::from(filter).as_slice());
+        }
+    }
+
+    #[test]
+    fn color_aovs_are_half() {
+        assert_eq!("half", Aov::Albedo.scalar_format());
+        assert_eq!("half", Aov::Custom("dirt".to_string()).scalar_format());
+        assert_eq!("dirt", Aov::Custom("dirt".to_string()).variable_name());
+    }
+}
@@ -0,0 +1,83 @@
+//! Reuse of nodes across helper calls.
+use std::collections::HashMap;
+
+/// Maps keys of your choosing to the handles of nodes that were
+/// already created.
+///
+/// Helpers called with a [`None`] handle create a new node each time.
+/// When calling them in a loop that is often not what you want. E.g.
+/// all objects of a kind may share one material, or several shots may
+/// share one environment. Ask the registry for the node under a key
+/// instead; it only runs the helper if no node was registered for
+/// that key yet.
+///
+/// The registry is opt-in. It does not look at the ɴsɪ context and the
+/// helpers do not know about it. A node deleted from the context
+/// must be [`remove()`](HandleRegistry::remove())d from the registry
+/// too.
+///
+/// # Example
+/// ```
+/// # use nsi_3delight::HandleRegistry;
+/// let mut registry = HandleRegistry::new();
+/// let mut created = 0;
+///
+/// for _ in 0..3 {
+///     // With a context this would be e.g.
+///     // `principled_material(&ctx, None, [0.8; 3], 0.5, 0.0, None, &[])`.
+///     registry.get_or_create("red_plastic", || {
+///         created += 1;
+///         "material_red_plastic".to_string()
+///     });
+/// }
+///
+/// assert_eq!(1, created);
+/// assert_eq!(Some("material_red_plastic"), registry.get("red_plastic"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandleRegistry {
+    handles: HashMap<String, String>,
+}
+
+impl HandleRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the handle registered under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.handles.get(key).map(String::as_str)
+    }
+
+    /// Returns the handle registered under `key`. If there is none,
+    /// `create` is called to create the node and its handle is
+    /// registered.
+    pub fn get_or_create<F>(&mut self, key: &str, create: F) -> &str
+    where
+        F: FnOnce() -> String,
+    {
+        self.handles
+            .entry(key.to_string())
+            .or_insert_with(create)
+            .as_str()
+    }
+
+    /// Registers `handle` under `key`.
+    ///
+    /// Returns the handle that was registered under `key` before, if
+    /// any.
+    pub fn insert(&mut self, key: &str, handle: &str) -> Option<String> {
+        self.handles.insert(key.to_string(), handle.to_string())
+    }
+
+    /// Forgets the handle registered under `key` and returns it.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.handles.remove(key)
+    }
+
+    /// Forgets all handles, e.g. after starting a new scene.
+    pub fn clear(&mut self) {
+        self.handles.clear();
+    }
+}
@@ -0,0 +1,232 @@
+//! Shortcuts for controlling rendering.
+
+/// What [`render()`] should do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderAction {
+    /// Starts rendering and returns immediately.
+    Start,
+    /// Starts rendering and returns when the render is done.
+    StartBlocking,
+    /// Suspends a running render.
+    Pause,
+    /// Resumes a suspended render.
+    Resume,
+    /// Stops a running render. It can not be resumed.
+    Stop,
+    /// Applies scene changes made since the last call to a running
+    /// interactive render.
+    Synchronize,
+}
+
+/// Controls rendering.
+///
+/// Each action maps to the `action` of
+/// [`render_control()`](nsi::Context::render_control()):
+///
+/// | [`RenderAction`] | `action`                      |
+/// | ---------------- | ----------------------------- |
+/// | `Start`          | `"start"`                     |
+/// | `StartBlocking`  | `"start"`, followed by `"wait"` |
+/// | `Pause`          | `"suspend"`                   |
+/// | `Resume`         | `"resume"`                    |
+/// | `Stop`           | `"stop"`                      |
+/// | `Synchronize`    | `"synchronize"`               |
+///
+/// [`RenderAction::StartBlocking`] is what batch renders and command
+/// line tools want: once it returns, all images have been written.
+/// With [`RenderAction::Start`] the render runs in the background and
+/// the [`Context`](nsi::Context) must be kept alive until it is done.
+pub fn render(ctx: &nsi::Context, action: RenderAction) {
+    let action = match action {
+        RenderAction::Start => "start",
+        RenderAction::StartBlocking => {
+            ctx.render_control(&[nsi::string!("action", "start")]);
+            "wait"
+        }
+        RenderAction::Pause => "suspend",
+        RenderAction::Resume => "resume",
+        RenderAction::Stop => "stop",
+        RenderAction::Synchronize => "synchronize",
+    };
+
+    ctx.render_control(&[nsi::string!("action", action)]);
+}
+
+/// Starts an interactive, progressive render and returns immediately.
+///
+/// This is what a viewport wants: the image is refined progressively
+/// and the render keeps running until [`stop()`] is called.
+///
+/// The lifecycle is:
+/// 1. `start_interactive()` once the scene is set up.
+/// 2. [`pause()`] before editing the scene.
+/// 3. [`resume()`] after editing it. The edits made while the render
+///    was paused are applied and the image restarts refining.
+/// 4. [`stop()`] when done. A stopped render can not be resumed; call
+///    `start_interactive()` again instead.
+pub fn start_interactive(ctx: &nsi::Context) {
+    ctx.render_control(&[
+        nsi::string!("action", "start"),
+        nsi::integer!("interactive", 1),
+        nsi::integer!("progressive", 1),
+    ]);
+}
+
+/// Suspends a render started with [`start_interactive()`].
+///
+/// Returns immediately.
+pub fn pause(ctx: &nsi::Context) {
+    ctx.render_control(&[nsi::string!("action", "suspend")]);
+}
+
+/// Applies any scene edits made since [`pause()`] and resumes the
+/// render.
+///
+/// Returns immediately.
+pub fn resume(ctx: &nsi::Context) {
+    ctx.render_control(&[nsi::string!("action", "synchronize")]);
+    ctx.render_control(&[nsi::string!("action", "resume")]);
+}
+
+/// Stops a render started with [`start_interactive()`].
+///
+/// Returns immediately.
+pub fn stop(ctx: &nsi::Context) {
+    ctx.render_control(&[nsi::string!("action", "stop")]);
+}
+
+/// Renders a layer of a screen into an 8 bit RGBA image.
+///
+/// This creates a temporary output layer with an
+/// [`output_driver_callback()`](crate::output_driver_callback()) on
+/// `screen`, renders with [`RenderAction::StartBlocking`] and deletes
+/// the layer and driver again.
+///
+/// # Arguments
+/// * `screen` – The handle of the screen to render, e.g. as returned
+///   by [`screen()`](crate::screen()).
+///
+/// * `layer` – The name of the variable to output, e.g. `"Ci"` for the
+///   beauty pass.
+///
+/// The pixels are delivered as 32 bit floats, also for layers rendered
+/// at half precision. They are un-premultiplied, converted from linear
+/// to sRGB and clipped to `0.0..=1.0` before being quantized. Layers
+/// without alpha are opaque.
+///
+/// If nothing was rendered, e.g. because the scene has no camera
+/// connected to `screen`, the image is empty.
+///
+/// This needs the `image` feature.
+#[cfg(feature = "image")]
+pub fn render_to_image(ctx: &nsi::Context, screen: &str, layer: &str) -> image::RgbaImage {
+    use std::sync::{Arc, Mutex};
+
+    let layer = crate::output_layer(ctx, None, layer, screen, &[nsi::integer!("withalpha", 1)]);
+
+    // Width, height, channels and pixels of the whole image.
+    let pixels = Arc::new(Mutex::new((0, 0, 0, Vec::new())));
+    let driver = {
+        let pixels = pixels.clone();
+        crate::output_driver_callback(ctx, None, layer.as_str(), move |info, bucket| {
+            let mut pixels = pixels.lock().unwrap();
+            let (width, height, channels, data) = &mut *pixels;
+            if data.is_empty() {
+                *width = info.image_width;
+                *height = info.image_height;
+                *channels = info.channels;
+                data.resize(info.image_width * info.image_height * info.channels, 0.0);
+            }
+
+            let row_length = info.width() * info.channels;
+            for (row, y) in (info.y_min..info.y_max).enumerate() {
+                let start = (y * info.image_width + info.x_min) * info.channels;
+                data[start..start + row_length]
+                    .copy_from_slice(&bucket[row * row_length..(row + 1) * row_length]);
+            }
+        })
+    };
+
+    render(ctx, RenderAction::StartBlocking);
+
+    ctx.delete(driver.as_str(), &[]);
+    ctx.delete(layer.as_str(), &[]);
+
+    let pixels = pixels.lock().unwrap();
+    let (width, height, channels, data) = &*pixels;
+    image::RgbaImage::from_fn(*width as _, *height as _, |x, y| {
+        let index = (y as usize * width + x as usize) * channels;
+        let pixel = &data[index..index + channels];
+        let (color, alpha) = match *channels {
+            1 | 2 => ([pixel[0]; 3], pixel.get(1).copied().unwrap_or(1.0)),
+            _ => (
+                [pixel[0], pixel[1], pixel[2]],
+                pixel.get(3).copied().unwrap_or(1.0),
+            ),
+        };
+
+        let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let color = color.map(|value| {
+            let value = if 0.0 < alpha { value / alpha } else { 0.0 };
+            to_u8(linear_to_srgb(value))
+        });
+
+        image::Rgba([color[0], color[1], color[2], to_u8(alpha)])
+    })
+}
+
+// The sRGB transfer function.
+#[cfg(feature = "image")]
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Reports the progress of a render.
+///
+/// This connects an
+/// [`output_driver_callback()`](crate::output_driver_callback()) to
+/// `layer` that counts the pixels of the buckets the renderer has
+/// finished.
+///
+/// # Arguments
+/// * `layer` – The handle of a layer that is rendered, e.g. as
+///   returned by [`output_layer()`](crate::output_layer()).
+///
+/// * `on_progress` – Called after each bucket with the fraction of the
+///   image that is done, in `0.0..=1.0`.
+///
+/// Returns the handle of the driver. Delete it to stop reporting.
+///
+/// Like with any driver callback, `on_progress` is called from one of
+/// the renderer's threads but never concurrently. It must be [`Send`]
+/// and should return quickly as the renderer waits for it.
+///
+/// For progressive renders the first pass already completes all
+/// buckets so progress reaches `1.0` early.
+///
+/// This needs the `output` feature.
+#[cfg(feature = "output")]
+pub fn set_progress_callback<'a, F>(
+    ctx: &nsi::Context<'a>,
+    layer: &str,
+    mut on_progress: F,
+) -> String
+where
+    F: FnMut(f32) + Send + 'a,
+{
+    let mut done = 0;
+    crate::output_driver_callback(ctx, None, layer, move |info, _| {
+        let total = info.image_width * info.image_height;
+        done = total.min(done + info.width() * info.height());
+
+        on_progress(if 0 == total {
+            1.0
+        } else {
+            done as f32 / total as f32
+        });
+    })
+}
@@ -0,0 +1,217 @@
+//! A builder for networks of OSL shaders.
+use crate::NsiHelperError;
+use nsi::toolbelt::generate_or_use_handle;
+use std::collections::HashMap;
+
+// Where the code of a node comes from.
+#[derive(Debug)]
+enum ShaderCode {
+    File(String),
+    Source(String),
+}
+
+#[derive(Debug)]
+struct ShaderGraphNode<'a, 'b> {
+    name: String,
+    code: ShaderCode,
+    args: nsi::ArgVec<'b, 'a>,
+}
+
+#[derive(Clone, Debug)]
+struct ShaderGraphConnection {
+    from: String,
+    from_port: String,
+    to: String,
+    to_port: String,
+}
+
+/// A network of OSL shaders that is created in one go.
+///
+/// Nodes are added under local names and connected by these names.
+/// [`instantiate()`](ShaderGraph::instantiate()) then creates a
+/// `shader` node for each and returns their handles.
+///
+/// The graph is checked before anything is created: connections must
+/// refer to nodes of the graph and ports must be valid OSL parameter
+/// names. For nodes added with
+/// [`source_node()`](ShaderGraph::source_node()) the ports must also
+/// be declared in the source. Shaders loaded from files are not
+/// inspected.
+///
+/// # Example
+/// ```
+/// # use nsi_3delight::ShaderGraph;
+/// let graph = ShaderGraph::new()
+///     .node("texture", "${DELIGHT}/osl/dlTexture", vec![])
+///     .node("material", "${DELIGHT}/osl/dlPrincipled", vec![])
+///     .connect("texture", "outColor", "material", "i_color")
+///     .connect("texture", "outColor", "bump", "i_color");
+///
+/// // There is no "bump" node.
+/// assert!(graph.validate().is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct ShaderGraph<'a, 'b> {
+    nodes: Vec<ShaderGraphNode<'a, 'b>>,
+    connections: Vec<ShaderGraphConnection>,
+}
+
+impl<'a, 'b> ShaderGraph<'a, 'b> {
+    /// Starts an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node running a compiled shader, e.g.
+    /// `"${DELIGHT}/osl/dlTexture"`, with `args` set on it.
+    pub fn node(mut self, name: &str, shader_file_name: &str, args: nsi::ArgVec<'b, 'a>) -> Self {
+        self.nodes.push(ShaderGraphNode {
+            name: name.to_string(),
+            code: ShaderCode::File(shader_file_name.to_string()),
+            args,
+        });
+        self
+    }
+
+    /// Adds a node running OSL `source` that is compiled on the fly,
+    /// with `args` set on it.
+    pub fn source_node(mut self, name: &str, source: &str, args: nsi::ArgVec<'b, 'a>) -> Self {
+        self.nodes.push(ShaderGraphNode {
+            name: name.to_string(),
+            code: ShaderCode::Source(source.to_string()),
+            args,
+        });
+        self
+    }
+
+    /// Connects the output `from_port` of the node `from` to the
+    /// input `to_port` of the node `to`.
+    pub fn connect(mut self, from: &str, from_port: &str, to: &str, to_port: &str) -> Self {
+        self.connections.push(ShaderGraphConnection {
+            from: from.to_string(),
+            from_port: from_port.to_string(),
+            to: to.to_string(),
+            to_port: to_port.to_string(),
+        });
+        self
+    }
+
+    /// Checks the graph without creating anything.
+    ///
+    /// # Errors
+    /// If two nodes have the same name or a connection refers to a
+    /// node or port that does not exist.
+    pub fn validate(&self) -> Result<(), NsiHelperError> {
+        for (index, node) in self.nodes.iter().enumerate() {
+            if self.nodes[..index]
+                .iter()
+                .any(|other| other.name == node.name)
+            {
+                return Err(NsiHelperError::InvalidShaderGraph(format!(
+                    "node {:?} is added more than once",
+                    node.name
+                )));
+            }
+        }
+
+        for connection in &self.connections {
+            self.check_port(&connection.from, &connection.from_port)?;
+            self.check_port(&connection.to, &connection.to_port)?;
+        }
+
+        Ok(())
+    }
+
+    // Checks that `node` exists and may have the parameter `port`.
+    fn check_port(&self, node: &str, port: &str) -> Result<(), NsiHelperError> {
+        let node = self
+            .nodes
+            .iter()
+            .find(|other| other.name == node)
+            .ok_or_else(|| {
+                NsiHelperError::InvalidShaderGraph(format!("there is no node {:?}", node))
+            })?;
+
+        // Array elements are connected as e.g. `color[1]`.
+        let parameter = port.split('[').next().unwrap_or_default();
+        let is_identifier = parameter
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || '_' == c)
+            && parameter
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || '_' == c);
+
+        let is_declared = match &node.code {
+            ShaderCode::File(_) => true,
+            ShaderCode::Source(source) => source
+                .split(|c: char| !(c.is_ascii_alphanumeric() || '_' == c))
+                .any(|token| token == parameter),
+        };
+
+        if is_identifier && is_declared {
+            Ok(())
+        } else {
+            Err(NsiHelperError::InvalidShaderGraph(format!(
+                "node {:?} has no port {:?}",
+                node.name, port
+            )))
+        }
+    }
+
+    /// Creates the nodes of the graph and connects them.
+    ///
+    /// Each node gets a random handle, prefixed with its name.
+    ///
+    /// Returns the handles of the created `shader` nodes, keyed by the
+    /// names they were added with.
+    ///
+    /// # Errors
+    /// See [`validate()`](ShaderGraph::validate()). No nodes are
+    /// created in this case.
+    pub fn instantiate(
+        &self,
+        ctx: &nsi::Context<'a>,
+    ) -> Result<HashMap<String, String>, NsiHelperError> {
+        self.validate()?;
+
+        let handles = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let shader = generate_or_use_handle(None, Some(node.name.as_str()));
+
+                ctx.create(shader.as_str(), nsi::NodeType::Shader, &[]);
+                ctx.set_attribute(
+                    shader.as_str(),
+                    &[match &node.code {
+                        ShaderCode::File(file_name) => {
+                            nsi::string!("shaderfilename", file_name.as_str())
+                        }
+                        ShaderCode::Source(source) => {
+                            nsi::string!("shadersource", source.as_str())
+                        }
+                    }],
+                );
+
+                if !node.args.is_empty() {
+                    ctx.set_attribute(shader.as_str(), &node.args);
+                }
+
+                (node.name.clone(), shader)
+            })
+            .collect::<HashMap<_, _>>();
+
+        for connection in &self.connections {
+            ctx.connect(
+                handles[&connection.from].as_str(),
+                connection.from_port.as_str(),
+                handles[&connection.to].as_str(),
+                connection.to_port.as_str(),
+                &[],
+            );
+        }
+
+        Ok(handles)
+    }
+}
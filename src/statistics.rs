@@ -0,0 +1,176 @@
+//! Render statistics.
+#[cfg(feature = "statistics")]
+use crate::NsiHelperError;
+#[cfg(feature = "statistics")]
+use std::{collections::HashMap, path::Path};
+
+/// The format of the file [`enable_statistics()`] writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    /// Machine-readable. See [`RenderStats`].
+    Json,
+    /// A human-readable report.
+    Text,
+}
+
+impl From<StatsFormat> for Vec<u8> {
+    #[inline]
+    fn from(format: StatsFormat) -> Self {
+        match format {
+            StatsFormat::Json => b"json".to_vec(),
+            StatsFormat::Text => b"text".to_vec(),
+        }
+    }
+}
+
+/// Makes the renderer write statistics, e.g. ray counts and timings,
+/// to a file.
+///
+/// # Arguments
+/// * `path` – The file to write. Environment variables in the form
+///   `${NAME}` are expanded by the renderer. A relative path is
+///   relative to the working directory of the process running the
+///   render, not to any scene file. An existing file is overwritten.
+///
+/// * `format` – Prefer [`StatsFormat::Json`] for anything but reading.
+///   Its numbers do not move around when they grow so files of two
+///   renders are easy to diff. With the `statistics` feature it can
+///   also be read back with [`RenderStats::from_file()`].
+///
+/// 3Delight writes the file when the render finishes, i.e. once a
+/// blocking render returns or after a `stop`.
+///
+/// This sets `statistics.filename` and `statistics.format` on
+/// `.global`. Only the former is part of the ɴsɪ documentation. The
+/// `statistics.format` attribute and its values are assumed; a
+/// renderer that does not know it will write its default report.
+///
+/// # Panics
+/// If `path` is empty.
+pub fn enable_statistics(ctx: &nsi::Context, path: &str, format: StatsFormat) {
+    assert!(!path.is_empty(), "The statistics path must not be empty.");
+
+    ctx.set_attribute(
+        ".global",
+        &[
+            nsi::string!("statistics.filename", path),
+            nsi::string!("statistics.format", format),
+        ],
+    );
+}
+
+/// Statistics of a finished render, as written by
+/// [`enable_statistics()`] with [`StatsFormat::Json`].
+///
+/// The numbers are read from the `rays` and `times` objects and from
+/// `memory.peak` of the JSON. This layout is assumed, not taken from
+/// the 3Delight documentation, which does not describe one. Anything
+/// that is missing is left empty or `0`, so a file with another
+/// layout parses into empty statistics rather than failing.
+#[cfg(feature = "statistics")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    /// The number of rays traced, by type, e.g. `"camera"` or
+    /// `"shadow"`.
+    pub ray_counts: HashMap<String, u64>,
+    /// The time spent in each phase of the render, in seconds, e.g.
+    /// `"scene_loading"` or `"rendering"`.
+    pub phase_times: HashMap<String, f64>,
+    /// The most memory the renderer used at any one time, in bytes.
+    pub peak_memory: u64,
+}
+
+#[cfg(feature = "statistics")]
+impl RenderStats {
+    /// Parses the statistics from `json`.
+    ///
+    /// # Errors
+    /// If `json` is not valid JSON.
+    pub fn from_json(json: &str) -> Result<Self, NsiHelperError> {
+        let value = serde_json::from_str::<serde_json::Value>(json)
+            .map_err(|error| NsiHelperError::Statistics(error.to_string()))?;
+
+        Ok(Self {
+            ray_counts: section(&value, "rays")
+                .filter_map(|(name, count)| Some((name.clone(), count.as_u64()?)))
+                .collect(),
+            phase_times: section(&value, "times")
+                .filter_map(|(name, time)| Some((name.clone(), time.as_f64()?)))
+                .collect(),
+            peak_memory: value
+                .pointer("/memory/peak")
+                .and_then(serde_json::Value::as_u64)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Reads the statistics from the file at `path`.
+    ///
+    /// # Errors
+    /// If the file cannot be read or is not valid JSON.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, NsiHelperError> {
+        Self::from_json(
+            &std::fs::read_to_string(path)
+                .map_err(|error| NsiHelperError::Statistics(error.to_string()))?,
+        )
+    }
+
+    /// Returns the number of rays of all types.
+    pub fn total_rays(&self) -> u64 {
+        self.ray_counts.values().sum()
+    }
+}
+
+// Iterates over the members of the object `name` in `value`, if any.
+#[cfg(feature = "statistics")]
+fn section<'v>(
+    value: &'v serde_json::Value,
+    name: &str,
+) -> impl Iterator<Item = (&'v String, &'v serde_json::Value)> + 'v {
+    value
+        .get(name)
+        .and_then(serde_json::Value::as_object)
+        .into_iter()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_format_names() {
+        assert_eq!(b"json".to_vec(), Vec::<u8>::from(StatsFormat::Json));
+        assert_eq!(b"text".to_vec(), Vec::<u8>::from(StatsFormat::Text));
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn render_stats_from_json() {
+        let stats = RenderStats::from_json(
+            r#"{
+                "rays": { "camera": 1000, "shadow": 2500, "label": "ignored" },
+                "times": { "scene_loading": 0.5, "rendering": 12.25 },
+                "memory": { "peak": 1073741824 }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(&1000), stats.ray_counts.get("camera"));
+        assert_eq!(Some(&2500), stats.ray_counts.get("shadow"));
+        assert_eq!(2, stats.ray_counts.len());
+        assert_eq!(3500, stats.total_rays());
+        assert_eq!(Some(&12.25), stats.phase_times.get("rendering"));
+        assert_eq!(1 << 30, stats.peak_memory);
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn render_stats_missing_sections() {
+        assert_eq!(
+            RenderStats::default(),
+            RenderStats::from_json("{}").unwrap()
+        );
+        assert!(RenderStats::from_json("{ rays").is_err());
+    }
+}
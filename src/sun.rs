@@ -0,0 +1,175 @@
+//! Physically based sun placement.
+use chrono::{DateTime, Utc};
+
+/// Computes the direction towards the sun.
+///
+/// # Arguments
+/// * `latitude` – In degrees; positive north of the equator.
+///
+/// * `longitude` – In degrees; positive east of Greenwich.
+///
+/// * `datetime` – The time of day and date.
+///
+/// The result is a normalized vector in a Y-up world where -Z points
+/// north and +X points east. It points *towards* the sun, i.e. it is
+/// the negated `direction` of a [`distant_light()`](crate::distant_light()).
+///
+/// This uses the low precision formulas from the Astronomical Almanac
+/// which are accurate to about 0.01° between 1950 and 2050.
+pub fn solar_vector(latitude: f64, longitude: f64, datetime: DateTime<Utc>) -> [f64; 3] {
+    let (azimuth, elevation) = solar_position(latitude, longitude, datetime);
+
+    [
+        elevation.cos() * azimuth.sin(),
+        elevation.sin(),
+        -elevation.cos() * azimuth.cos(),
+    ]
+}
+
+// Returns the azimuth (clockwise from north) and elevation of the sun,
+// in radians.
+fn solar_position(latitude: f64, longitude: f64, datetime: DateTime<Utc>) -> (f64, f64) {
+    // Days since J2000.0.
+    let n = (datetime.timestamp() as f64 + datetime.timestamp_subsec_millis() as f64 / 1000.0)
+        / 86400.0
+        + 2440587.5
+        - 2451545.0;
+
+    // Ecliptic coordinates.
+    let mean_longitude = (280.460 + 0.9856474 * n).rem_euclid(360.0);
+    let mean_anomaly = (357.528 + 0.9856003 * n).rem_euclid(360.0).to_radians();
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin())
+            .to_radians();
+    let obliquity = (23.439 - 0.0000004 * n).to_radians();
+
+    // Equatorial coordinates.
+    let right_ascension =
+        (obliquity.cos() * ecliptic_longitude.sin()).atan2(ecliptic_longitude.cos());
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+
+    // Horizontal coordinates.
+    let sidereal_time = (18.697374558 + 24.06570982441908 * n).rem_euclid(24.0) * 15.0;
+    let hour_angle = (sidereal_time + longitude).to_radians() - right_ascension;
+    let latitude = latitude.to_radians();
+
+    let elevation = (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .asin();
+    let azimuth = (-declination.cos() * hour_angle.sin()).atan2(
+        declination.sin() * latitude.cos() - declination.cos() * hour_angle.cos() * latitude.sin(),
+    );
+
+    (azimuth, elevation)
+}
+
+// Returns the `azimuth` and `elevation` of the `dlSky` shader, in
+// degrees.
+fn sky_angles(latitude: f64, longitude: f64, datetime: DateTime<Utc>) -> (f32, f32) {
+    let (azimuth, elevation) = solar_position(latitude, longitude, datetime);
+
+    (
+        azimuth.to_degrees().rem_euclid(360.0) as f32,
+        elevation.to_degrees() as f32,
+    )
+}
+
+/// Creates a procedural sky environment light with the sun placed for
+/// a location on earth at a given time.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `latitude` – In degrees; positive north of the equator.
+///
+/// * `longitude` – In degrees; positive east of Greenwich.
+///
+/// * `datetime` – The time of day and date.
+///
+/// * `exposure` – Scales the intensity in
+///   [stops or EV values](https://en.wikipedia.org/wiki/Exposure_value).
+///
+/// * `visible` – If the environment is visible to the camera.
+///
+/// Returns the handles of the transform and the created `shader`.
+///
+/// The sun's position is set via the `dlSky` shader's `azimuth` and
+/// `elevation` attributes (in degrees). North is along -Z; see
+/// [`solar_vector()`].
+///
+/// # Errors
+/// If `handle` is invalid or `latitude`, `longitude` or `exposure`
+/// are not finite.
+#[allow(clippy::too_many_arguments)]
+pub fn sun_sky<'a, 'b>(
+    ctx: &nsi::Context<'a>,
+    handle: Option<&str>,
+    latitude: f64,
+    longitude: f64,
+    datetime: DateTime<Utc>,
+    exposure: Option<f32>,
+    visible: Option<bool>,
+    args: &nsi::ArgSlice<'b, 'a>,
+) -> Result<crate::EnvironmentHandles, crate::NsiHelperError> {
+    crate::error::check_finite("latitude", latitude)?;
+    crate::error::check_finite("longitude", longitude)?;
+
+    let handles = crate::environment_sky(ctx, handle, None, None, exposure, visible, &[])?;
+
+    let (azimuth, elevation) = sky_angles(latitude, longitude, datetime);
+
+    ctx.set_attribute(
+        handles.shader.as_str(),
+        &[
+            nsi::float!("azimuth", azimuth),
+            nsi::float!("elevation", elevation),
+        ],
+    );
+
+    if !args.is_empty() {
+        ctx.set_attribute(handles.shader.as_str(), args);
+    }
+
+    Ok(handles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // The Royal Observatory, Greenwich.
+    const LATITUDE: f64 = 51.4769;
+    const LONGITUDE: f64 = -0.0005;
+
+    fn solstice_noon() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2000, 6, 21, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn solar_position_matches_ephemeris() {
+        let (azimuth, elevation) = solar_position(LATITUDE, LONGITUDE, solstice_noon());
+        let (azimuth, elevation) = (azimuth.to_degrees(), elevation.to_degrees());
+
+        assert!((elevation - 61.9).abs() < 1.0, "{}", elevation);
+        assert!((azimuth - 180.0).abs() < 1.0, "{}", azimuth);
+    }
+
+    #[test]
+    fn solar_vector_points_south_and_up() {
+        let [x, y, z] = solar_vector(LATITUDE, LONGITUDE, solstice_noon());
+
+        assert!(x.abs() < 0.02);
+        assert!((y - 61.9_f64.to_radians().sin()).abs() < 0.01);
+        // North is along -Z.
+        assert!(0.0 < z);
+    }
+
+    #[test]
+    fn sky_angles_are_in_degrees() {
+        let (azimuth, elevation) = sky_angles(LATITUDE, LONGITUDE, solstice_noon());
+
+        assert!((azimuth - 180.0).abs() < 1.0, "{}", azimuth);
+        assert!((elevation - 61.9).abs() < 1.0, "{}", elevation);
+    }
+}
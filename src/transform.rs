@@ -0,0 +1,242 @@
+//! Shortcuts for instancing transforms.
+use crate::math;
+use nsi::toolbelt::generate_or_use_handle;
+
+/// Creates a transform from a raw matrix.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `matrix` – The 4×4 transformation matrix in column-major order.
+///   I.e. elements 0–3 are the image of the X axis, 4–7 that of the
+///   Y axis, 8–11 that of the Z axis and the translation is in
+///   elements 12–14. Element 15 is usually `1`.
+///
+///   This is the memory layout of e.g. [ultraviolet](https://docs.rs/ultraviolet)
+///   and Houdini's matrices and what Maya's `xform -q -m` prints. A
+///   row-major matrix, with the translation in elements 3, 7 and 11,
+///   must be transposed first.
+///
+/// Returns the handle of the created transform.
+///
+/// All other transform helpers in this crate build on this.
+pub fn transform_matrix(ctx: &nsi::Context, handle: Option<&str>, matrix: [f64; 16]) -> String {
+    let transform = generate_or_use_handle(handle, Some("transform"));
+
+    ctx.create(transform.as_str(), nsi::NodeType::Transform, &[]);
+    ctx.set_attribute(
+        transform.as_str(),
+        &[nsi::double_matrix!("transformationmatrix", &matrix)],
+    );
+
+    transform
+}
+
+/// Creates a translation transform.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `offset` – The translation along the X, Y and Z axes.
+///
+/// Returns the handle of the created transform. Nothing is connected
+/// to it; append it to its parent and hook e.g. a camera or light
+/// below it.
+pub fn translation(ctx: &nsi::Context, handle: Option<&str>, offset: [f64; 3]) -> String {
+    transform_matrix(ctx, handle, math::translation(offset))
+}
+
+/// Creates a scale transform.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `factors` – The scale along the X, Y and Z axes. These do not
+///   need to be uniform.
+///
+///   A negative factor mirrors along its axis. This flips the
+///   handedness of the transform – and therefore the orientation of
+///   the surface normals of any geometry below it.
+///
+/// Returns the handle of the created transform.
+pub fn scale(ctx: &nsi::Context, handle: Option<&str>, factors: [f64; 3]) -> String {
+    transform_matrix(ctx, handle, math::scale(factors))
+}
+
+/// Creates a transform at `eye` that looks towards `target`.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `eye` – The position of the transform's origin.
+///
+/// * `target` – The point the transform's -Z axis is aimed at.
+///
+/// * `up` – The direction the transform's Y axis leans towards. This
+///   does not need to be perpendicular to the view direction nor
+///   normalized. If it is parallel to the view direction, the Z (or,
+///   when looking along Z, the X) axis is used instead.
+///
+/// Returns the handle of the created transform. Cameras and lights
+/// look down -Z so this is ready to parent e.g. a
+/// [`perspective_camera()`](crate::perspective_camera()) or a
+/// [`spot_light()`](crate::spot_light()).
+///
+/// # Panics
+/// If `eye` and `target` are the same point.
+pub fn look_at(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    eye: [f64; 3],
+    target: [f64; 3],
+    up: [f64; 3],
+) -> String {
+    assert!(eye != target, "The eye ({:?}) is at the target.", eye);
+
+    transform_matrix(ctx, handle, math::look_at(eye, target, up))
+}
+
+// Returns the transform that scales, then rotates (around X, Y and Z,
+// in this order) and finally translates.
+fn trs_matrix(translate: [f64; 3], rotate_euler_deg: [f64; 3], scale: [f64; 3]) -> [f64; 16] {
+    let rotation = math::multiply(
+        &math::multiply(
+            &math::rotation(rotate_euler_deg[0], [1.0, 0.0, 0.0]),
+            &math::rotation(rotate_euler_deg[1], [0.0, 1.0, 0.0]),
+        ),
+        &math::rotation(rotate_euler_deg[2], [0.0, 0.0, 1.0]),
+    );
+
+    math::multiply(
+        &math::multiply(&math::scale(scale), &rotation),
+        &math::translation(translate),
+    )
+}
+
+/// Creates a transform from a translation, rotation and scale.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `translate` – The translation along the X, Y and Z axes.
+///
+/// * `rotate_euler_deg` – In degrees; the rotation around the X, Y
+///   and Z axes. These are applied in X-Y-Z order, like Maya's
+///   default rotation order.
+///
+/// * `scale` – The scale along the X, Y and Z axes.
+///
+/// Returns the handle of the created transform.
+///
+/// The scale is applied first, then the rotation and finally the
+/// translation. I.e. the object is scaled and rotated around its own
+/// origin before being moved into place.
+pub fn transform_trs(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    translate: [f64; 3],
+    rotate_euler_deg: [f64; 3],
+    scale: [f64; 3],
+) -> String {
+    transform_matrix(ctx, handle, trs_matrix(translate, rotate_euler_deg, scale))
+}
+
+/// Creates a rotation transform from a quaternion.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `quat` – The rotation as `[w, x, y, z]`, i.e. with the real part
+///   first. The quaternion is normalized so it does not need to be of
+///   unit length. A zero-length quaternion yields no rotation.
+///
+/// Returns the handle of the created transform.
+pub fn rotation_quaternion(ctx: &nsi::Context, handle: Option<&str>, quat: [f64; 4]) -> String {
+    transform_matrix(ctx, handle, math::from_quaternion(quat))
+}
+
+/// Creates a transform that moves during the shutter interval, for
+/// motion blur.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `matrix_open`, `matrix_close` – The matrices at the times the
+///   shutter opens and closes. See [`transform_matrix()`] for the
+///   expected element order.
+///
+/// * `shutter` – The times the shutter opens and closes. These should
+///   match the `shutterrange` of the camera, see
+///   [`set_shutter()`](crate::set_shutter()).
+///
+/// Returns the handle of the created transform.
+///
+/// For motion along a curve, use [`transform_motion_samples()`] with
+/// more than two samples.
+pub fn transform_motion(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    matrix_open: [f64; 16],
+    matrix_close: [f64; 16],
+    shutter: [f64; 2],
+) -> String {
+    transform_motion_samples(
+        ctx,
+        handle,
+        &[(shutter[0], matrix_open), (shutter[1], matrix_close)],
+    )
+}
+
+/// Creates a transform with a matrix at each of the given times, for
+/// motion blur.
+///
+/// If `handle` is [`None`] a random handle is generated.
+///
+/// # Arguments
+/// * `samples` – The time and the matrix at that time, for each
+///   sample. The times should cover the `shutterrange` of the camera.
+///
+/// Returns the handle of the created transform.
+///
+/// # Panics
+/// If `samples` is empty.
+pub fn transform_motion_samples(
+    ctx: &nsi::Context,
+    handle: Option<&str>,
+    samples: &[(f64, [f64; 16])],
+) -> String {
+    assert!(!samples.is_empty(), "No motion samples given.");
+
+    let transform = generate_or_use_handle(handle, Some("transform"));
+
+    ctx.create(transform.as_str(), nsi::NodeType::Transform, &[]);
+    for (time, matrix) in samples {
+        ctx.set_attribute_at_time(
+            transform.as_str(),
+            *time,
+            &[nsi::double_matrix!("transformationmatrix", matrix)],
+        );
+    }
+
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::tests::{assert_close, transform_point};
+
+    #[test]
+    fn trs_scales_then_rotates_then_translates() {
+        let m = trs_matrix([10.0, 0.0, 0.0], [0.0, 90.0, 0.0], [2.0, 1.0, 1.0]);
+
+        assert_close([10.0, 0.0, -2.0], transform_point(&m, [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn trs_rotates_around_x_first() {
+        let m = trs_matrix([0.0; 3], [90.0, 90.0, 0.0], [1.0; 3]);
+
+        assert_close([1.0, 0.0, 0.0], transform_point(&m, [0.0, 1.0, 0.0]));
+    }
+}